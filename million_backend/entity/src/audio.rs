@@ -8,11 +8,29 @@ pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i32,
     pub url: String,
-    pub length_millis: i32,
+    pub title: Option<String>,
+    pub mime_type: Option<String>,
+    pub length_millis: Option<i32>,
+    pub source: i32,
     pub created_at: DateTime,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
-pub enum Relation {}
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::websites::Entity",
+        from = "Column::Source",
+        to = "super::websites::Column::Id",
+        on_update = "NoAction",
+        on_delete = "NoAction"
+    )]
+    Websites,
+}
+
+impl Related<super::websites::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Websites.def()
+    }
+}
 
 impl ActiveModelBehavior for ActiveModel {}
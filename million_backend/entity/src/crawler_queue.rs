@@ -14,6 +14,10 @@ pub struct Model {
     pub expiry: Option<DateTime>,
     pub last_updated: DateTime,
     pub created_at: DateTime,
+    pub published_at: Option<DateTime>,
+    pub guard: Option<String>,
+    pub redirect_url: Option<String>,
+    pub attempts: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
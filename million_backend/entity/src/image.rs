@@ -2,34 +2,51 @@
 
 use sea_orm::entity::prelude::*;
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
 #[sea_orm(table_name = "image")]
 pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i32,
     pub url: String,
-    pub source: i32,
     pub width: Option<i32>,
     pub height: Option<i32>,
     pub alt_text: Option<String>,
     pub created_at: DateTime,
+    pub perceptual_hash: Option<i64>,
+    #[sea_orm(column_type = "Float", nullable)]
+    pub min_luminance: Option<f32>,
+    #[sea_orm(column_type = "Float", nullable)]
+    pub max_luminance: Option<f32>,
+    #[sea_orm(column_type = "Float", nullable)]
+    pub mean_luminance: Option<f32>,
+    pub dominant_colors: Vec<String>,
+    pub has_thumbnail: bool,
+    pub normalized_url: String,
+    pub content_hash: Option<Vec<u8>>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub context: Option<String>,
+    #[sea_orm(column_type = "JsonBinary")]
+    pub variants: Json,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
-    #[sea_orm(
-        belongs_to = "super::websites::Entity",
-        from = "Column::Source",
-        to = "super::websites::Column::Id",
-        on_update = "NoAction",
-        on_delete = "NoAction"
-    )]
-    Websites,
+    #[sea_orm(has_many = "super::image_source::Entity")]
+    ImageSource,
+}
+
+impl Related<super::image_source::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ImageSource.def()
+    }
 }
 
 impl Related<super::websites::Entity> for Entity {
     fn to() -> RelationDef {
-        Relation::Websites.def()
+        super::image_source::Relation::Websites.def()
+    }
+    fn via() -> Option<RelationDef> {
+        Some(super::image_source::Relation::Image.def().rev())
     }
 }
 
@@ -2,8 +2,13 @@
 
 pub mod prelude;
 
+pub mod anchor;
+pub mod audio;
 pub mod crawler_queue;
+pub mod feed;
 pub mod image;
+pub mod image_source;
 pub mod sea_orm_active_enums;
 pub mod search_history;
+pub mod video;
 pub mod websites;
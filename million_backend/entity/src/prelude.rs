@@ -1,6 +1,11 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.15
 
+pub use super::anchor::Entity as Anchor;
+pub use super::audio::Entity as Audio;
 pub use super::crawler_queue::Entity as CrawlerQueue;
+pub use super::feed::Entity as Feed;
 pub use super::image::Entity as Image;
+pub use super::image_source::Entity as ImageSource;
 pub use super::search_history::Entity as SearchHistory;
+pub use super::video::Entity as Video;
 pub use super::websites::Entity as Websites;
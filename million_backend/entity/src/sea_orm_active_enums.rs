@@ -2,6 +2,21 @@
 
 use sea_orm::entity::prelude::*;
 
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "document_type")]
+pub enum DocumentType {
+    #[sea_orm(string_value = "html")]
+    Html,
+    #[sea_orm(string_value = "markdown")]
+    Markdown,
+    #[sea_orm(string_value = "pdf")]
+    Pdf,
+    #[sea_orm(string_value = "plain_text")]
+    PlainText,
+    #[sea_orm(string_value = "xml")]
+    Xml,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
 #[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "status")]
 pub enum Status {
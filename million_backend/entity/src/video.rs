@@ -8,13 +8,32 @@ pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i32,
     pub url: String,
-    pub width: i32,
-    pub height: i32,
-    pub length_millis: i32,
+    pub title: Option<String>,
+    pub mime_type: Option<String>,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub length_millis: Option<i32>,
+    pub poster_url: Option<String>,
+    pub source: i32,
     pub created_at: DateTime,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
-pub enum Relation {}
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::websites::Entity",
+        from = "Column::Source",
+        to = "super::websites::Column::Id",
+        on_update = "NoAction",
+        on_delete = "NoAction"
+    )]
+    Websites,
+}
+
+impl Related<super::websites::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Websites.def()
+    }
+}
 
 impl ActiveModelBehavior for ActiveModel {}
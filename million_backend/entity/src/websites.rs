@@ -1,5 +1,6 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.15
 
+use super::sea_orm_active_enums::DocumentType;
 use sea_orm::entity::prelude::*;
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
@@ -18,18 +19,64 @@ pub struct Model {
     pub site_short_name: Option<String>,
     pub site_description: Option<String>,
     pub site_categories: Vec<String>,
+    pub anchor_texts: Vec<String>,
+    pub language: Option<String>,
+    #[sea_orm(column_type = "JsonBinary", nullable)]
+    pub json_ld: Option<Json>,
+    pub og_title: Option<String>,
+    pub og_description: Option<String>,
+    pub og_image: Option<String>,
+    pub og_type: Option<String>,
+    pub og_site_name: Option<String>,
+    pub og_url: Option<String>,
+    pub twitter_card: Option<String>,
+    pub twitter_title: Option<String>,
+    pub twitter_description: Option<String>,
+    pub twitter_image: Option<String>,
+    pub twitter_site: Option<String>,
+    pub twitter_creator: Option<String>,
+    pub document_type: DocumentType,
+    pub author: Option<String>,
+    pub page_count: Option<i32>,
     pub created_at: DateTime,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
-    #[sea_orm(has_many = "super::image::Entity")]
-    Image,
+    #[sea_orm(has_many = "super::audio::Entity")]
+    Audio,
+    #[sea_orm(has_many = "super::image_source::Entity")]
+    ImageSource,
+    #[sea_orm(has_many = "super::video::Entity")]
+    Video,
+}
+
+impl Related<super::audio::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Audio.def()
+    }
+}
+
+impl Related<super::image_source::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ImageSource.def()
+    }
 }
 
 impl Related<super::image::Entity> for Entity {
     fn to() -> RelationDef {
-        Relation::Image.def()
+        super::image_source::Relation::Image.def()
+    }
+    fn via() -> Option<RelationDef> {
+        Some(super::image_source::Relation::Websites.def().rev())
+    }
+}
+
+impl Related<super::video::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Video.def()
     }
 }
 
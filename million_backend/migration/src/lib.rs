@@ -3,6 +3,23 @@ pub use sea_orm_migration::prelude::*;
 mod m20220101_000001_create_table;
 mod m20240505_025740_more_types;
 mod m20240507_082145_search_history;
+mod m20240601_000000_anchor_text;
+mod m20240602_000000_website_language;
+mod m20240603_000000_structured_data;
+mod m20240604_000000_document_type;
+mod m20240605_000000_text_document_types;
+mod m20240606_000000_feed;
+mod m20240607_000000_media;
+mod m20240608_000000_image_hash;
+mod m20240609_000000_image_color;
+mod m20240610_000000_image_thumbnail;
+mod m20240611_000000_image_dedup;
+mod m20240612_000000_image_context;
+mod m20240613_000000_image_variants;
+mod m20240614_000000_crawl_guard;
+mod m20240615_000000_conditional_requests;
+mod m20240616_000000_redirects;
+mod m20240617_000000_crawl_attempts;
 
 pub struct Migrator;
 
@@ -13,6 +30,23 @@ impl MigratorTrait for Migrator {
             Box::new(m20220101_000001_create_table::Migration),
             Box::new(m20240505_025740_more_types::Migration),
             Box::new(m20240507_082145_search_history::Migration),
+            Box::new(m20240601_000000_anchor_text::Migration),
+            Box::new(m20240602_000000_website_language::Migration),
+            Box::new(m20240603_000000_structured_data::Migration),
+            Box::new(m20240604_000000_document_type::Migration),
+            Box::new(m20240605_000000_text_document_types::Migration),
+            Box::new(m20240606_000000_feed::Migration),
+            Box::new(m20240607_000000_media::Migration),
+            Box::new(m20240608_000000_image_hash::Migration),
+            Box::new(m20240609_000000_image_color::Migration),
+            Box::new(m20240610_000000_image_thumbnail::Migration),
+            Box::new(m20240611_000000_image_dedup::Migration),
+            Box::new(m20240612_000000_image_context::Migration),
+            Box::new(m20240613_000000_image_variants::Migration),
+            Box::new(m20240614_000000_crawl_guard::Migration),
+            Box::new(m20240615_000000_conditional_requests::Migration),
+            Box::new(m20240616_000000_redirects::Migration),
+            Box::new(m20240617_000000_crawl_attempts::Migration),
         ]
     }
 }
@@ -145,11 +145,35 @@ pub enum Websites {
     SiteDescription,
     SiteCategories,
 
+    AnchorTexts,
+    Language,
+
+    JsonLd,
+    OgTitle,
+    OgDescription,
+    OgImage,
+    OgType,
+    OgSiteName,
+    OgUrl,
+    TwitterCard,
+    TwitterTitle,
+    TwitterDescription,
+    TwitterImage,
+    TwitterSite,
+    TwitterCreator,
+
+    DocumentType,
+    Author,
+    PageCount,
+
     CreatedAt,
+
+    Etag,
+    LastModified,
 }
 
 #[derive(DeriveIden)]
-enum CrawlerQueue {
+pub enum CrawlerQueue {
     Table,
     Id,
     Url,
@@ -157,6 +181,14 @@ enum CrawlerQueue {
     Expiry,
     LastUpdated,
     CreatedAt,
+
+    PublishedAt,
+
+    Guard,
+
+    RedirectUrl,
+
+    Attempts,
 }
 
 #[derive(DeriveIden, EnumIter)]
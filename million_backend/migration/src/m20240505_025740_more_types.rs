@@ -57,7 +57,7 @@ impl MigrationTrait for Migration {
 }
 
 #[derive(DeriveIden)]
-enum Image {
+pub enum Image {
     Table,
     Id,
 
@@ -69,4 +69,19 @@ enum Image {
     Source,
 
     CreatedAt,
+
+    PerceptualHash,
+
+    MinLuminance,
+    MaxLuminance,
+    MeanLuminance,
+    DominantColors,
+
+    HasThumbnail,
+
+    NormalizedUrl,
+    ContentHash,
+
+    Context,
+    Variants,
 }
@@ -0,0 +1,101 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20220101_000001_create_table::Websites;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Anchor::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Anchor::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Anchor::SourceUrl).string().not_null())
+                    .col(ColumnDef::new(Anchor::TargetUrl).string().not_null())
+                    .col(ColumnDef::new(Anchor::Text).string().not_null())
+                    .col(
+                        ColumnDef::new(Anchor::CreatedAt)
+                            .timestamp()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Anchor text has no length limit, and a btree entry over the raw text fails once
+        // it passes a third of a page, so uniqueness is checked on its hash instead.
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"CREATE UNIQUE INDEX "idx-anchor-source_url-target_url-text_hash"
+                ON "anchor" ("source_url", "target_url", (md5("text")))"#,
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-anchor-target_url")
+                    .table(Anchor::Table)
+                    .col(Anchor::TargetUrl)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Websites::Table)
+                    .add_column(
+                        ColumnDef::new(Websites::AnchorTexts)
+                            .array(ColumnType::String(None))
+                            .not_null()
+                            .default(Expr::cust("'{}'")),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Websites::Table)
+                    .drop_column(Websites::AnchorTexts)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(Anchor::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Anchor {
+    Table,
+    Id,
+
+    SourceUrl,
+    TargetUrl,
+    Text,
+
+    CreatedAt,
+}
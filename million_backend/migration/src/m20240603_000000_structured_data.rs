@@ -0,0 +1,59 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20220101_000001_create_table::Websites;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Websites::Table)
+                    .add_column(ColumnDef::new(Websites::JsonLd).json_binary())
+                    .add_column(ColumnDef::new(Websites::OgTitle).string())
+                    .add_column(ColumnDef::new(Websites::OgDescription).string())
+                    .add_column(ColumnDef::new(Websites::OgImage).string())
+                    .add_column(ColumnDef::new(Websites::OgType).string())
+                    .add_column(ColumnDef::new(Websites::OgSiteName).string())
+                    .add_column(ColumnDef::new(Websites::OgUrl).string())
+                    .add_column(ColumnDef::new(Websites::TwitterCard).string())
+                    .add_column(ColumnDef::new(Websites::TwitterTitle).string())
+                    .add_column(ColumnDef::new(Websites::TwitterDescription).string())
+                    .add_column(ColumnDef::new(Websites::TwitterImage).string())
+                    .add_column(ColumnDef::new(Websites::TwitterSite).string())
+                    .add_column(ColumnDef::new(Websites::TwitterCreator).string())
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Websites::Table)
+                    .drop_column(Websites::JsonLd)
+                    .drop_column(Websites::OgTitle)
+                    .drop_column(Websites::OgDescription)
+                    .drop_column(Websites::OgImage)
+                    .drop_column(Websites::OgType)
+                    .drop_column(Websites::OgSiteName)
+                    .drop_column(Websites::OgUrl)
+                    .drop_column(Websites::TwitterCard)
+                    .drop_column(Websites::TwitterTitle)
+                    .drop_column(Websites::TwitterDescription)
+                    .drop_column(Websites::TwitterImage)
+                    .drop_column(Websites::TwitterSite)
+                    .drop_column(Websites::TwitterCreator)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
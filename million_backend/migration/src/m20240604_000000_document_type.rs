@@ -0,0 +1,70 @@
+use sea_orm_migration::{
+    prelude::*,
+    sea_orm::{EnumIter, Iterable},
+    sea_query::extension::postgres::{Type, TypeDropStatement},
+};
+
+use crate::m20220101_000001_create_table::Websites;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(DocumentType)
+                    .values(DocumentTypeVariants::iter())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Websites::Table)
+                    .add_column(
+                        ColumnDef::new(Websites::DocumentType)
+                            .custom(DocumentType)
+                            .not_null()
+                            .default(Expr::cust("'html'")),
+                    )
+                    .add_column(ColumnDef::new(Websites::Author).string())
+                    .add_column(ColumnDef::new(Websites::PageCount).integer())
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Websites::Table)
+                    .drop_column(Websites::DocumentType)
+                    .drop_column(Websites::Author)
+                    .drop_column(Websites::PageCount)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_type(TypeDropStatement::new().name(DocumentType).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden, EnumIter)]
+enum DocumentTypeVariants {
+    Html,
+    Pdf,
+}
+
+#[derive(DeriveIden)]
+pub struct DocumentType;
@@ -0,0 +1,40 @@
+use sea_orm_migration::{prelude::*, sea_query::extension::postgres::Type};
+
+use crate::m20240604_000000_document_type::DocumentType;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for variant in [
+            DocumentTypeVariants::PlainText,
+            DocumentTypeVariants::Markdown,
+            DocumentTypeVariants::Xml,
+        ] {
+            manager
+                .alter_type(
+                    Type::alter()
+                        .name(DocumentType)
+                        .add_value(variant)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        // Postgres cannot drop values from an enum type.
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum DocumentTypeVariants {
+    PlainText,
+    Markdown,
+    Xml,
+}
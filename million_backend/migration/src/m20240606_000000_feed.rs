@@ -0,0 +1,83 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20220101_000001_create_table::CrawlerQueue;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Feed::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Feed::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Feed::Url).string().not_null().unique_key())
+                    .col(ColumnDef::new(Feed::Title).string())
+                    .col(ColumnDef::new(Feed::LastPolledAt).timestamp())
+                    .col(
+                        ColumnDef::new(Feed::NextPollAt)
+                            .timestamp()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Feed::CreatedAt)
+                            .timestamp()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CrawlerQueue::Table)
+                    .add_column(ColumnDef::new(CrawlerQueue::PublishedAt).timestamp())
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CrawlerQueue::Table)
+                    .drop_column(CrawlerQueue::PublishedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(Feed::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Feed {
+    Table,
+    Id,
+    Url,
+    Title,
+
+    LastPolledAt,
+    NextPollAt,
+
+    CreatedAt,
+}
@@ -0,0 +1,125 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20220101_000001_create_table::Websites;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Video::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Video::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Video::Url).string().not_null())
+                    .col(ColumnDef::new(Video::Title).string())
+                    .col(ColumnDef::new(Video::MimeType).string())
+                    .col(ColumnDef::new(Video::Width).integer())
+                    .col(ColumnDef::new(Video::Height).integer())
+                    .col(ColumnDef::new(Video::LengthMillis).integer())
+                    .col(ColumnDef::new(Video::PosterUrl).string())
+                    .col(ColumnDef::new(Video::Source).integer().not_null())
+                    .col(
+                        ColumnDef::new(Video::CreatedAt)
+                            .timestamp()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(Video::Table, Video::Source)
+                            .to(Websites::Table, Websites::Id),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(Audio::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Audio::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Audio::Url).string().not_null())
+                    .col(ColumnDef::new(Audio::Title).string())
+                    .col(ColumnDef::new(Audio::MimeType).string())
+                    .col(ColumnDef::new(Audio::LengthMillis).integer())
+                    .col(ColumnDef::new(Audio::Source).integer().not_null())
+                    .col(
+                        ColumnDef::new(Audio::CreatedAt)
+                            .timestamp()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(Audio::Table, Audio::Source)
+                            .to(Websites::Table, Websites::Id),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Audio::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(Video::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Video {
+    Table,
+    Id,
+
+    Url,
+    Title,
+    MimeType,
+    Width,
+    Height,
+    LengthMillis,
+    PosterUrl,
+
+    Source,
+
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Audio {
+    Table,
+    Id,
+
+    Url,
+    Title,
+    MimeType,
+    LengthMillis,
+
+    Source,
+
+    CreatedAt,
+}
@@ -0,0 +1,35 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20240505_025740_more_types::Image;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Image::Table)
+                    .add_column(ColumnDef::new(Image::PerceptualHash).big_integer())
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Image::Table)
+                    .drop_column(Image::PerceptualHash)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
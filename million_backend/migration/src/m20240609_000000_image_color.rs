@@ -0,0 +1,46 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20240505_025740_more_types::Image;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Image::Table)
+                    .add_column(ColumnDef::new(Image::MinLuminance).float())
+                    .add_column(ColumnDef::new(Image::MaxLuminance).float())
+                    .add_column(ColumnDef::new(Image::MeanLuminance).float())
+                    .add_column(
+                        ColumnDef::new(Image::DominantColors)
+                            .array(ColumnType::String(None))
+                            .not_null()
+                            .default(Expr::cust("'{}'")),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Image::Table)
+                    .drop_column(Image::MinLuminance)
+                    .drop_column(Image::MaxLuminance)
+                    .drop_column(Image::MeanLuminance)
+                    .drop_column(Image::DominantColors)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
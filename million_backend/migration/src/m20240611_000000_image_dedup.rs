@@ -0,0 +1,176 @@
+use sea_orm_migration::prelude::*;
+
+use crate::{m20220101_000001_create_table::Websites, m20240505_025740_more_types::Image};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ImageSource::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(ImageSource::ImageId).integer().not_null())
+                    .col(ColumnDef::new(ImageSource::WebsiteId).integer().not_null())
+                    .col(ColumnDef::new(ImageSource::AltText).string())
+                    .col(
+                        ColumnDef::new(ImageSource::CreatedAt)
+                            .timestamp()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(ImageSource::ImageId)
+                            .col(ImageSource::WebsiteId),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(ImageSource::Table, ImageSource::ImageId)
+                            .to(Image::Table, Image::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(ImageSource::Table, ImageSource::WebsiteId)
+                            .to(Websites::Table, Websites::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-image_source-website_id")
+                    .table(ImageSource::Table)
+                    .col(ImageSource::WebsiteId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Every existing occurrence becomes a link to the oldest row with the same url,
+        // and the other rows are dropped.
+        let db = manager.get_connection();
+
+        db.execute_unprepared(
+            r#"INSERT INTO "image_source" ("image_id", "website_id", "alt_text")
+            SELECT "canonical"."id", "image"."source", "image"."alt_text"
+            FROM "image"
+            JOIN (SELECT "url", MIN("id") AS "id" FROM "image" GROUP BY "url") AS "canonical"
+                ON "canonical"."url" = "image"."url"
+            ON CONFLICT DO NOTHING"#,
+        )
+        .await?;
+
+        db.execute_unprepared(
+            r#"DELETE FROM "image"
+            WHERE "id" NOT IN (SELECT MIN("id") FROM "image" GROUP BY "url")"#,
+        )
+        .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Image::Table)
+                    .drop_column(Image::Source)
+                    .add_column(
+                        ColumnDef::new(Image::NormalizedUrl)
+                            .string()
+                            .not_null()
+                            .default(""),
+                    )
+                    .add_column(ColumnDef::new(Image::ContentHash).binary())
+                    .to_owned(),
+            )
+            .await?;
+
+        db.execute_unprepared(r#"UPDATE "image" SET "normalized_url" = "url""#)
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-image-normalized_url")
+                    .table(Image::Table)
+                    .col(Image::NormalizedUrl)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-image-content_hash")
+                    .table(Image::Table)
+                    .col(Image::ContentHash)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Image::Table)
+                    .add_column(ColumnDef::new(Image::Source).integer())
+                    .drop_column(Image::NormalizedUrl)
+                    .drop_column(Image::ContentHash)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Canonical images keep a single source page.
+        let db = manager.get_connection();
+
+        db.execute_unprepared(
+            r#"UPDATE "image" SET "source" = (
+                SELECT MIN("website_id") FROM "image_source"
+                WHERE "image_source"."image_id" = "image"."id"
+            )"#,
+        )
+        .await?;
+
+        db.execute_unprepared(r#"DELETE FROM "image" WHERE "source" IS NULL"#)
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Image::Table)
+                    .modify_column(ColumnDef::new(Image::Source).integer().not_null())
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .from_tbl(Image::Table)
+                            .from_col(Image::Source)
+                            .to_tbl(Websites::Table)
+                            .to_col(Websites::Id),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(ImageSource::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum ImageSource {
+    Table,
+    ImageId,
+    WebsiteId,
+    AltText,
+    CreatedAt,
+}
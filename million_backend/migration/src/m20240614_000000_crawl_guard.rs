@@ -0,0 +1,35 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20220101_000001_create_table::CrawlerQueue;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CrawlerQueue::Table)
+                    .add_column(ColumnDef::new(CrawlerQueue::Guard).string())
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CrawlerQueue::Table)
+                    .drop_column(CrawlerQueue::Guard)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,37 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20220101_000001_create_table::Websites;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Websites::Table)
+                    .add_column(ColumnDef::new(Websites::Etag).string())
+                    .add_column(ColumnDef::new(Websites::LastModified).string())
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Websites::Table)
+                    .drop_column(Websites::Etag)
+                    .drop_column(Websites::LastModified)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
@@ -1,28 +1,33 @@
 use std::str::FromStr;
 
 use chrono::{Duration, NaiveDateTime, Utc};
-use entity::sea_orm_active_enums::Status as JobStatus;
-use entity::{crawler_queue, websites};
-use meilisearch_sdk::client::Client;
+use entity::sea_orm_active_enums::{DocumentType, Status as JobStatus};
+use entity::{anchor, crawler_queue, websites};
 use proto::{
     crawler::{
         return_job_request::{self},
-        GetJobRequest, GetJobResponse, KeepAliveJobRequest, KeepAliveJobResponse, ReturnJobRequest,
-        ReturnJobResponse,
+        GetJobRequest, GetJobResponse, Guard, KeepAliveJobRequest, KeepAliveJobResponse,
+        ReturnJobRequest, ReturnJobResponse,
     },
     tonic::{self, Response, Status},
 };
 use sea_orm::{
     ActiveModelTrait, ActiveValue, ColumnTrait, Condition, DatabaseConnection, EntityTrait,
-    QueryFilter,
+    QueryFilter, QuerySelect,
 };
-use sea_query::{IntoIden, SimpleExpr};
+use sea_query::{Expr, IntoIden, OnConflict, SimpleExpr};
 use url::Url;
 
+use crate::{feed, images, thumbnail::ThumbnailStore};
+
+/// Times a job may time out before it is given up on.
+const MAX_TIMEOUT_ATTEMPTS: i32 = 3;
+
 #[derive(Debug)]
 pub struct CrawlerServise {
     pub db: DatabaseConnection,
-    pub search_client: Client,
+    pub feed_poll_interval: Duration,
+    pub thumbnails: ThumbnailStore,
 }
 
 #[tonic::async_trait]
@@ -70,9 +75,25 @@ impl proto::crawler::crawler_server::Crawler for CrawlerServise {
             .await
             .map_err(|err| Status::from_error(err.into()))?;
 
+        // Validators from the last crawl let the crawler skip pages that have not changed.
+        // A URL that redirected was stored under where it redirected to.
+        let stored_url = task.redirect_url.as_deref().unwrap_or(&task.url);
+        let cached = websites::Entity::find()
+            .select_only()
+            .columns([websites::Column::Etag, websites::Column::LastModified])
+            .filter(websites::Column::Url.eq(stored_url))
+            .into_tuple::<(Option<String>, Option<String>)>()
+            .one(&self.db)
+            .await
+            .map_err(|err| Status::from_error(err.into()))?;
+        let (etag, last_modified) = cached.unwrap_or_default();
+
         Ok(Response::new(GetJobResponse {
             id: task.id,
             url: task.url,
+            etag,
+            last_modified,
+            validated_url: task.redirect_url,
         }))
     }
 
@@ -82,15 +103,41 @@ impl proto::crawler::crawler_server::Crawler for CrawlerServise {
     ) -> std::result::Result<tonic::Response<ReturnJobResponse>, tonic::Status> {
         let request = request.into_inner();
 
-        let result = if let Some(return_job_request::Result::Ok(result)) = request.result {
-            result
-        } else {
-            //TODO: increment attemps
-            return Ok(Response::new(ReturnJobResponse {}));
+        let result = match request.result {
+            Some(return_job_request::Result::Ok(result)) => result,
+            Some(return_job_request::Result::Err(err)) => {
+                if err.guard() != Guard::None {
+                    record_guard(&self.db, request.id, &request.url, err.guard())
+                        .await
+                        .map_err(|err| Status::from_error(err.into()))?;
+                }
+
+                //TODO: increment attemps
+                return Ok(Response::new(ReturnJobResponse {}));
+            }
+            // The stored crawl is still current, so only the job's timestamps change.
+            Some(return_job_request::Result::NotModified(_)) => {
+                complete_job(&self.db, request.id, &request.url, None)
+                    .await
+                    .map_err(|err| Status::from_error(err.into()))?;
+
+                return Ok(Response::new(ReturnJobResponse {}));
+            }
+            None => return Ok(Response::new(ReturnJobResponse {})),
         };
 
         let _url = Url::from_str(&request.url).map_err(|err| Status::from_error(err.into()))?;
 
+        // Pages are indexed under the URL they were served from, not the alias that was queued.
+        let page_url = if result.final_url.is_empty() {
+            request.url.clone()
+        } else {
+            let mut url =
+                Url::from_str(&result.final_url).map_err(|err| Status::from_error(err.into()))?;
+            url.set_fragment(None);
+            url.to_string()
+        };
+
         let task = crawler_queue::Entity::find_by_id(request.id)
             .filter(crawler_queue::Column::Url.eq(&request.url))
             .one(&self.db)
@@ -106,27 +153,21 @@ impl proto::crawler::crawler_server::Crawler for CrawlerServise {
             return Err(Status::invalid_argument("task expired"));
         }
 
-        crawler_queue::Entity::update_many()
-            .col_expr(
-                crawler_queue::Column::Status,
-                SimpleExpr::AsEnum(
-                    entity::sea_orm_active_enums::StatusEnum.into_iden(),
-                    Box::new(JobStatus::Complete.into()),
-                ),
-            )
-            .col_expr(
-                crawler_queue::Column::Expiry,
-                Option::<NaiveDateTime>::None.into(),
-            )
-            .col_expr(
-                crawler_queue::Column::LastUpdated,
-                chrono::Utc::now().naive_utc().into(),
+        complete_job(&self.db, request.id, &request.url, None)
+            .await
+            .map_err(|err| Status::from_error(err.into()))?;
+
+        if page_url != request.url {
+            record_redirects(
+                &self.db,
+                &self.thumbnails,
+                &request.url,
+                &result.redirects,
+                &page_url,
             )
-            .filter(crawler_queue::Column::Id.eq(request.id))
-            .filter(crawler_queue::Column::Url.eq(&request.url))
-            .exec(&self.db)
             .await
             .map_err(|err| Status::from_error(err.into()))?;
+        }
 
         for url in result.linked_urls {
             let mut url = url
@@ -156,9 +197,52 @@ impl proto::crawler::crawler_server::Crawler for CrawlerServise {
                 .map_err(|err| Status::from_error(err.into()))?;
         }
 
+        update_anchors(&self.db, &page_url, result.anchors)
+            .await
+            .map_err(|err| Status::from_error(err.into()))?;
+
+        feed::register_feeds(&self.db, result.feed_urls)
+            .await
+            .map_err(|err| Status::from_error(err.into()))?;
+
+        if let Some(feed) = result.feed {
+            feed::record_feed(
+                &self.db,
+                &request.url,
+                &page_url,
+                feed,
+                self.feed_poll_interval,
+            )
+            .await
+            .map_err(|err| Status::from_error(err.into()))?;
+        }
+
         if let Some(html_body) = result.body {
+            let json_ld = (!html_body.json_ld.is_empty())
+                .then(|| {
+                    html_body
+                        .json_ld
+                        .iter()
+                        .map(|json_ld| serde_json::from_str(json_ld))
+                        .collect::<Result<Vec<serde_json::Value>, _>>()
+                        .map(serde_json::Value::Array)
+                })
+                .transpose()
+                .map_err(|err| Status::invalid_argument(err.to_string()))?;
+
+            let document_type = match html_body.document_type() {
+                proto::crawler::DocumentType::Html => DocumentType::Html,
+                proto::crawler::DocumentType::Pdf => DocumentType::Pdf,
+                proto::crawler::DocumentType::PlainText => DocumentType::PlainText,
+                proto::crawler::DocumentType::Markdown => DocumentType::Markdown,
+                proto::crawler::DocumentType::Xml => DocumentType::Xml,
+            };
+
+            let open_graph = html_body.open_graph.unwrap_or_default();
+            let twitter_card = html_body.twitter_card.unwrap_or_default();
+
             let website = websites::ActiveModel {
-                url: ActiveValue::Set(request.url.clone()),
+                url: ActiveValue::Set(page_url.clone()),
                 title: ActiveValue::Set(html_body.title),
                 description: ActiveValue::Set(html_body.description),
                 icon_url: ActiveValue::Set(html_body.icon_url),
@@ -166,6 +250,27 @@ impl proto::crawler::crawler_server::Crawler for CrawlerServise {
                 text_fields: ActiveValue::Set(html_body.text_fields),
                 sections: ActiveValue::Set(html_body.sections),
                 keywords: ActiveValue::Set(html_body.keywords),
+                language: ActiveValue::Set(html_body.language),
+
+                json_ld: ActiveValue::Set(json_ld),
+                og_title: ActiveValue::Set(open_graph.title),
+                og_description: ActiveValue::Set(open_graph.description),
+                og_image: ActiveValue::Set(open_graph.image),
+                og_type: ActiveValue::Set(open_graph.r#type),
+                og_site_name: ActiveValue::Set(open_graph.site_name),
+                og_url: ActiveValue::Set(open_graph.url),
+                twitter_card: ActiveValue::Set(twitter_card.card),
+                twitter_title: ActiveValue::Set(twitter_card.title),
+                twitter_description: ActiveValue::Set(twitter_card.description),
+                twitter_image: ActiveValue::Set(twitter_card.image),
+                twitter_site: ActiveValue::Set(twitter_card.site),
+                twitter_creator: ActiveValue::Set(twitter_card.creator),
+
+                document_type: ActiveValue::Set(document_type),
+                author: ActiveValue::Set(html_body.author),
+                page_count: ActiveValue::Set(html_body.page_count.map(|count| count as i32)),
+                etag: ActiveValue::Set(result.etag),
+                last_modified: ActiveValue::Set(result.last_modified),
 
                 site_name: ActiveValue::Set(
                     html_body
@@ -195,36 +300,62 @@ impl proto::crawler::crawler_server::Crawler for CrawlerServise {
 
                 ..Default::default()
             };
+            remove_website(&self.db, &self.thumbnails, &page_url)
+                .await
+                .map_err(|err| Status::from_error(err.into()))?;
+
             let website = website
                 .insert(&self.db)
                 .await
                 .map_err(|err| Status::from_error(err.into()))?;
 
+            refresh_anchor_texts(&self.db, vec![website.url.clone()])
+                .await
+                .map_err(|err| Status::from_error(err.into()))?;
+
             for img in html_body.images {
-                //TODO - Remove duplicates
-                //TODO - Don't add images without text
+                images::store_image(&self.db, &self.thumbnails, website.id, img)
+                    .await
+                    .map_err(|err| Status::from_error(err.into()))?;
+            }
 
-                let (width, height) = img
+            for video in html_body.videos {
+                let (width, height) = video
                     .size
                     .map(|size| (Some(size.width), Some(size.height)))
                     .unwrap_or_default();
 
-                // let (min_luminance, max_luminance) = img
-                //     .luminance_range
-                //     .map(|range| (Some(range.min), Some(range.max)))
-                //     .unwrap_or_default();
-
-                let image = entity::image::ActiveModel {
-                    url: ActiveValue::Set(img.image_url),
+                let video = entity::video::ActiveModel {
+                    url: ActiveValue::Set(video.url),
+                    title: ActiveValue::Set(video.title),
+                    mime_type: ActiveValue::Set(video.mime_type),
                     width: ActiveValue::Set(width),
                     height: ActiveValue::Set(height),
-                    alt_text: ActiveValue::Set(img.alt_text),
+                    length_millis: ActiveValue::Set(
+                        video.length_millis.map(|length| length as i32),
+                    ),
+                    poster_url: ActiveValue::Set(video.poster_url),
                     source: ActiveValue::Set(website.id),
-                    // min_luminance: ActiveValue::Set(min_luminance),
-                    // max_luminance: ActiveValue::Set(max_luminance),
                     ..Default::default()
                 };
-                image
+                video
+                    .insert(&self.db)
+                    .await
+                    .map_err(|err| Status::from_error(err.into()))?;
+            }
+
+            for audio in html_body.audios {
+                let audio = entity::audio::ActiveModel {
+                    url: ActiveValue::Set(audio.url),
+                    title: ActiveValue::Set(audio.title),
+                    mime_type: ActiveValue::Set(audio.mime_type),
+                    length_millis: ActiveValue::Set(
+                        audio.length_millis.map(|length| length as i32),
+                    ),
+                    source: ActiveValue::Set(website.id),
+                    ..Default::default()
+                };
+                audio
                     .insert(&self.db)
                     .await
                     .map_err(|err| Status::from_error(err.into()))?;
@@ -258,3 +389,304 @@ impl proto::crawler::crawler_server::Crawler for CrawlerServise {
         Ok(Response::new(KeepAliveJobResponse {}))
     }
 }
+
+/// Records a job the crawler gave up on because of one of its resource limits. A page
+/// trips size, redirect and address limits every time, so those complete the job for
+/// good. A timeout may be a passing slowdown, so the job is queued again until it has
+/// timed out [`MAX_TIMEOUT_ATTEMPTS`] times.
+async fn record_guard(
+    db: &DatabaseConnection,
+    id: i32,
+    url: &str,
+    guard: Guard,
+) -> anyhow::Result<()> {
+    let Some(task) = crawler_queue::Entity::find_by_id(id)
+        .filter(crawler_queue::Column::Url.eq(url))
+        .filter(crawler_queue::Column::Status.eq(JobStatus::Executing))
+        .one(db)
+        .await?
+    else {
+        return Ok(());
+    };
+
+    let attempts = task.attempts + 1;
+    let retry = guard == Guard::Timeout && attempts < MAX_TIMEOUT_ATTEMPTS;
+
+    let guard = guard
+        .as_str_name()
+        .trim_start_matches("GUARD_")
+        .to_ascii_lowercase();
+
+    if retry {
+        tracing::info!("crawl of {} stopped by {}, retrying later", url, guard);
+    } else {
+        tracing::warn!("crawl of {} stopped by {}", url, guard);
+    }
+
+    let status = if retry {
+        JobStatus::Queued
+    } else {
+        JobStatus::Complete
+    };
+
+    finish_job(db, id, url, status, Some(guard), attempts).await
+}
+
+/// Marks an executing job as done, recording the guard that stopped it, if any.
+async fn complete_job(
+    db: &DatabaseConnection,
+    id: i32,
+    url: &str,
+    guard: Option<String>,
+) -> anyhow::Result<()> {
+    finish_job(db, id, url, JobStatus::Complete, guard, 0).await
+}
+
+/// Ends the current run of an executing job, either completing it or queueing it to be
+/// crawled again. The job was fetched at its own URL, so any redirect recorded for it is
+/// cleared; `record_redirects` sets it again if the URL still redirects.
+async fn finish_job(
+    db: &DatabaseConnection,
+    id: i32,
+    url: &str,
+    status: JobStatus,
+    guard: Option<String>,
+    attempts: i32,
+) -> anyhow::Result<()> {
+    crawler_queue::Entity::update_many()
+        .col_expr(
+            crawler_queue::Column::Status,
+            SimpleExpr::AsEnum(
+                entity::sea_orm_active_enums::StatusEnum.into_iden(),
+                Box::new(status.into()),
+            ),
+        )
+        .col_expr(
+            crawler_queue::Column::Expiry,
+            Option::<NaiveDateTime>::None.into(),
+        )
+        .col_expr(
+            crawler_queue::Column::LastUpdated,
+            Utc::now().naive_utc().into(),
+        )
+        .col_expr(crawler_queue::Column::Guard, guard.into())
+        .col_expr(crawler_queue::Column::Attempts, attempts.into())
+        .col_expr(
+            crawler_queue::Column::RedirectUrl,
+            Option::<String>::None.into(),
+        )
+        .filter(crawler_queue::Column::Id.eq(id))
+        .filter(crawler_queue::Column::Url.eq(url))
+        .filter(crawler_queue::Column::Status.eq(JobStatus::Executing))
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
+/// Marks every URL that redirected to `page_url` as an alias of it and drops anything
+/// indexed under the aliases, so a redirecting URL never shows up next to its target.
+/// The target itself is marked as crawled so links to it are not queued again, unless
+/// it is already queued or being crawled.
+async fn record_redirects(
+    db: &DatabaseConnection,
+    thumbnails: &ThumbnailStore,
+    queued_url: &str,
+    redirects: &[String],
+    page_url: &str,
+) -> anyhow::Result<()> {
+    let now = Utc::now().naive_utc();
+
+    let mut aliases = vec![queued_url.to_owned()];
+    for url in redirects {
+        let mut url = url.parse::<Url>()?;
+        url.set_fragment(None);
+        aliases.push(url.to_string());
+    }
+    aliases.sort();
+    aliases.dedup();
+    aliases.retain(|alias| alias != page_url);
+
+    for alias in &aliases {
+        crawler_queue::Entity::insert(crawler_queue::ActiveModel {
+            url: ActiveValue::Set(alias.clone()),
+            status: ActiveValue::Set(JobStatus::Complete),
+            last_updated: ActiveValue::Set(now),
+            redirect_url: ActiveValue::Set(Some(page_url.to_owned())),
+            ..Default::default()
+        })
+        .on_conflict(
+            OnConflict::column(crawler_queue::Column::Url)
+                .update_columns([
+                    crawler_queue::Column::Status,
+                    crawler_queue::Column::Expiry,
+                    crawler_queue::Column::LastUpdated,
+                    crawler_queue::Column::RedirectUrl,
+                ])
+                .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+
+        remove_website(db, thumbnails, alias).await?;
+        update_anchors(db, alias, vec![]).await?;
+    }
+
+    // A queued or running crawl of the target is left to finish on its own.
+    crawler_queue::Entity::insert(crawler_queue::ActiveModel {
+        url: ActiveValue::Set(page_url.to_owned()),
+        status: ActiveValue::Set(JobStatus::Complete),
+        last_updated: ActiveValue::Set(now),
+        ..Default::default()
+    })
+    .on_conflict(
+        OnConflict::column(crawler_queue::Column::Url)
+            .update_columns([
+                crawler_queue::Column::Status,
+                crawler_queue::Column::Expiry,
+                crawler_queue::Column::LastUpdated,
+                crawler_queue::Column::RedirectUrl,
+            ])
+            .action_and_where(
+                crawler_queue::Column::Status.is_not_in([JobStatus::Executing, JobStatus::Queued]),
+            )
+            .to_owned(),
+    )
+    .exec_without_returning(db)
+    .await?;
+
+    Ok(())
+}
+
+/// Deletes an earlier crawl of `url` and its media so a re-crawled page replaces it.
+async fn remove_website(
+    db: &DatabaseConnection,
+    thumbnails: &ThumbnailStore,
+    url: &str,
+) -> anyhow::Result<()> {
+    let ids = websites::Entity::find()
+        .select_only()
+        .column(websites::Column::Id)
+        .filter(websites::Column::Url.eq(url))
+        .into_tuple::<i32>()
+        .all(db)
+        .await?;
+
+    if ids.is_empty() {
+        return Ok(());
+    }
+
+    images::unlink_images(db, thumbnails, ids.clone()).await?;
+
+    entity::video::Entity::delete_many()
+        .filter(entity::video::Column::Source.is_in(ids.clone()))
+        .exec(db)
+        .await?;
+
+    entity::audio::Entity::delete_many()
+        .filter(entity::audio::Column::Source.is_in(ids.clone()))
+        .exec(db)
+        .await?;
+
+    websites::Entity::delete_many()
+        .filter(websites::Column::Id.is_in(ids))
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
+/// Replaces the outbound anchors recorded for `source_url` and refreshes the
+/// aggregated anchor text of every page they point to.
+async fn update_anchors(
+    db: &DatabaseConnection,
+    source_url: &str,
+    anchors: Vec<return_job_request::ok::Anchor>,
+) -> anyhow::Result<()> {
+    let mut targets = anchor::Entity::find()
+        .select_only()
+        .column(anchor::Column::TargetUrl)
+        .filter(anchor::Column::SourceUrl.eq(source_url))
+        .into_tuple::<String>()
+        .all(db)
+        .await?;
+
+    anchor::Entity::delete_many()
+        .filter(anchor::Column::SourceUrl.eq(source_url))
+        .exec(db)
+        .await?;
+
+    let mut new_anchors = Vec::new();
+
+    for link in anchors {
+        let mut url = link.url.parse::<Url>()?;
+        url.set_fragment(None);
+
+        if url.as_str() == source_url {
+            continue;
+        }
+
+        targets.push(url.to_string());
+        new_anchors.push(anchor::ActiveModel {
+            source_url: ActiveValue::Set(source_url.to_owned()),
+            target_url: ActiveValue::Set(url.to_string()),
+            text: ActiveValue::Set(link.text),
+            ..Default::default()
+        });
+    }
+
+    if !new_anchors.is_empty() {
+        anchor::Entity::insert_many(new_anchors)
+            .on_conflict(
+                OnConflict::new()
+                    .exprs([
+                        Expr::col(anchor::Column::SourceUrl).into(),
+                        Expr::col(anchor::Column::TargetUrl).into(),
+                        Expr::cust(r#"(md5("text"))"#),
+                    ])
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .exec_without_returning(db)
+            .await?;
+    }
+
+    targets.sort();
+    targets.dedup();
+
+    refresh_anchor_texts(db, targets).await
+}
+
+/// Recomputes `websites.anchor_texts` for the pages at `urls`, or the pages they redirect
+/// to, from every inbound anchor, including anchors that point at a URL redirecting to
+/// the page. All pages are updated in one statement.
+async fn refresh_anchor_texts(db: &DatabaseConnection, urls: Vec<String>) -> anyhow::Result<()> {
+    if urls.is_empty() {
+        return Ok(());
+    }
+
+    websites::Entity::update_many()
+        .col_expr(
+            websites::Column::AnchorTexts,
+            Expr::cust(
+                r#"ARRAY(SELECT DISTINCT "text" FROM "anchor" WHERE "target_url" = "websites"."url" OR "target_url" IN (SELECT "url" FROM "crawler_queue" WHERE "redirect_url" = "websites"."url"))"#,
+            ),
+        )
+        .filter(
+            Condition::any()
+                .add(websites::Column::Url.is_in(urls.clone()))
+                .add(
+                    websites::Column::Url.in_subquery(
+                        sea_query::Query::select()
+                            .column(crawler_queue::Column::RedirectUrl)
+                            .from(crawler_queue::Entity)
+                            .and_where(crawler_queue::Column::Url.is_in(urls))
+                            .to_owned(),
+                    ),
+                ),
+        )
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
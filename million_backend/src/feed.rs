@@ -0,0 +1,352 @@
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use entity::{crawler_queue, feed, sea_orm_active_enums::Status as JobStatus};
+use proto::crawler::return_job_request;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter,
+    Select,
+};
+use sea_query::{IntoIden, OnConflict, SimpleExpr};
+use url::Url;
+
+/// How often the poller looks for feeds that are due.
+const POLL_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Registers feeds advertised by a page. Known feeds keep their schedule, and a feed URL
+/// that is known to redirect is registered under its target.
+pub async fn register_feeds(db: &DatabaseConnection, feed_urls: Vec<String>) -> anyhow::Result<()> {
+    let mut urls = Vec::new();
+    for url in feed_urls {
+        let mut url = url.parse::<Url>()?;
+        url.set_fragment(None);
+        urls.push(url.to_string());
+    }
+
+    if urls.is_empty() {
+        return Ok(());
+    }
+
+    let redirects = crawler_queue::Entity::find()
+        .filter(crawler_queue::Column::Url.is_in(urls.clone()))
+        .filter(crawler_queue::Column::RedirectUrl.is_not_null())
+        .all(db)
+        .await?;
+
+    let mut feeds = Vec::new();
+    for url in urls {
+        let url = redirects
+            .iter()
+            .find(|task| task.url == url)
+            .and_then(|task| task.redirect_url.clone())
+            .unwrap_or(url);
+
+        feeds.push(feed::ActiveModel {
+            url: ActiveValue::Set(url),
+            ..Default::default()
+        });
+    }
+
+    feed::Entity::insert_many(feeds)
+        .on_conflict(
+            OnConflict::column(feed::Column::Url)
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+
+    Ok(())
+}
+
+/// Stores a crawled feed and queues its items. Items that were already crawled are only
+/// queued again when the feed reports a newer publication date for them. A feed queued
+/// at `queued_url` that redirected to `url` is kept under `url` alone.
+pub async fn record_feed(
+    db: &DatabaseConnection,
+    queued_url: &str,
+    url: &str,
+    result: return_job_request::ok::Feed,
+    poll_interval: Duration,
+) -> anyhow::Result<()> {
+    let now = Utc::now().naive_utc();
+
+    if queued_url != url {
+        feed::Entity::delete_many()
+            .filter(feed::Column::Url.eq(queued_url))
+            .exec(db)
+            .await?;
+    }
+
+    feed::Entity::insert(feed::ActiveModel {
+        url: ActiveValue::Set(url.to_owned()),
+        title: ActiveValue::Set(result.title),
+        last_polled_at: ActiveValue::Set(Some(now)),
+        next_poll_at: ActiveValue::Set(now + poll_interval),
+        ..Default::default()
+    })
+    .on_conflict(
+        OnConflict::column(feed::Column::Url)
+            .update_columns([
+                feed::Column::Title,
+                feed::Column::LastPolledAt,
+                feed::Column::NextPollAt,
+            ])
+            .to_owned(),
+    )
+    .exec_without_returning(db)
+    .await?;
+
+    for item in result.items {
+        let mut item_url = item.url.parse::<Url>()?;
+        item_url.set_fragment(None);
+
+        let published_at = item
+            .published_at
+            .and_then(|timestamp| DateTime::from_timestamp(timestamp, 0))
+            .map(|published_at| published_at.naive_utc());
+
+        let task = crawler_queue::Entity::find()
+            .filter(crawler_queue::Column::Url.eq(item_url.as_str()))
+            .one(db)
+            .await?;
+
+        match item_action(task.as_ref(), published_at) {
+            QueueAction::Insert => {
+                crawler_queue::ActiveModel {
+                    url: ActiveValue::Set(item_url.to_string()),
+                    status: ActiveValue::Set(JobStatus::Queued),
+                    published_at: ActiveValue::Set(published_at),
+                    ..Default::default()
+                }
+                .insert(db)
+                .await?;
+            }
+            QueueAction::Requeue(id) => requeue(db, id, published_at).await?,
+            QueueAction::Skip => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Periodically queues every feed whose next poll time has passed.
+pub async fn poll_feeds(db: DatabaseConnection, poll_interval: Duration) {
+    let mut interval = tokio::time::interval(POLL_CHECK_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        if let Err(err) = queue_due_feeds(&db, poll_interval).await {
+            tracing::error!("failed to queue feeds: {}", err);
+        }
+    }
+}
+
+async fn queue_due_feeds(db: &DatabaseConnection, poll_interval: Duration) -> anyhow::Result<()> {
+    let now = Utc::now().naive_utc();
+
+    let feeds = due_feeds(now).all(db).await?;
+
+    for feed in feeds {
+        let task = crawler_queue::Entity::find()
+            .filter(crawler_queue::Column::Url.eq(&feed.url))
+            .one(db)
+            .await?;
+
+        match feed_action(task.as_ref()) {
+            QueueAction::Insert => {
+                crawler_queue::ActiveModel {
+                    url: ActiveValue::Set(feed.url.clone()),
+                    status: ActiveValue::Set(JobStatus::Queued),
+                    ..Default::default()
+                }
+                .insert(db)
+                .await?;
+            }
+            QueueAction::Requeue(id) => requeue(db, id, None).await?,
+            QueueAction::Skip => {}
+        }
+
+        feed::Entity::update_many()
+            .col_expr(feed::Column::NextPollAt, (now + poll_interval).into())
+            .filter(feed::Column::Id.eq(feed.id))
+            .exec(db)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Feeds whose next poll time has passed.
+fn due_feeds(now: NaiveDateTime) -> Select<feed::Entity> {
+    feed::Entity::find().filter(feed::Column::NextPollAt.lte(now))
+}
+
+/// What to do with the queue entry of a URL that should be crawled.
+#[derive(Debug, PartialEq)]
+enum QueueAction {
+    Insert,
+    Requeue(i32),
+    Skip,
+}
+
+/// A due feed is crawled again unless a crawl of it is already queued or running.
+fn feed_action(task: Option<&crawler_queue::Model>) -> QueueAction {
+    match task {
+        None => QueueAction::Insert,
+        Some(task) if task.status == JobStatus::Complete => QueueAction::Requeue(task.id),
+        Some(_) => QueueAction::Skip,
+    }
+}
+
+/// A feed item is crawled if it is new, or if it was crawled before the publication
+/// date the feed now reports for it.
+fn item_action(
+    task: Option<&crawler_queue::Model>,
+    published_at: Option<NaiveDateTime>,
+) -> QueueAction {
+    let Some(task) = task else {
+        return QueueAction::Insert;
+    };
+    let Some(published_at) = published_at else {
+        return QueueAction::Skip;
+    };
+
+    let crawled_at = task.published_at.unwrap_or(task.last_updated);
+
+    if task.status == JobStatus::Complete && published_at > crawled_at {
+        QueueAction::Requeue(task.id)
+    } else {
+        QueueAction::Skip
+    }
+}
+
+async fn requeue(
+    db: &DatabaseConnection,
+    id: i32,
+    published_at: Option<NaiveDateTime>,
+) -> anyhow::Result<()> {
+    let mut update = crawler_queue::Entity::update_many()
+        .col_expr(
+            crawler_queue::Column::Status,
+            SimpleExpr::AsEnum(
+                entity::sea_orm_active_enums::StatusEnum.into_iden(),
+                Box::new(JobStatus::Queued.into()),
+            ),
+        )
+        .col_expr(
+            crawler_queue::Column::LastUpdated,
+            Utc::now().naive_utc().into(),
+        );
+
+    if let Some(published_at) = published_at {
+        update = update.col_expr(
+            crawler_queue::Column::PublishedAt,
+            Some(published_at).into(),
+        );
+    }
+
+    update
+        .filter(crawler_queue::Column::Id.eq(id))
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use sea_orm::{DbBackend, QueryTrait};
+
+    use super::*;
+
+    fn at(hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 6, 1)
+            .unwrap()
+            .and_hms_opt(hour, 0, 0)
+            .unwrap()
+    }
+
+    fn task(status: JobStatus, published_at: Option<NaiveDateTime>) -> crawler_queue::Model {
+        crawler_queue::Model {
+            id: 7,
+            url: String::from("https://example.com/post"),
+            status,
+            expiry: None,
+            last_updated: at(12),
+            created_at: at(0),
+            published_at,
+            guard: None,
+            redirect_url: None,
+            attempts: 0,
+        }
+    }
+
+    #[test]
+    fn selects_feeds_past_their_poll_time() {
+        let sql = due_feeds(at(12)).build(DbBackend::Postgres).to_string();
+
+        assert!(
+            sql.ends_with(r#"WHERE "feed"."next_poll_at" <= '2024-06-01 12:00:00'"#),
+            "{}",
+            sql
+        );
+    }
+
+    #[test]
+    fn queues_due_feeds_that_are_not_already_crawling() {
+        assert_eq!(feed_action(None), QueueAction::Insert);
+        assert_eq!(
+            feed_action(Some(&task(JobStatus::Complete, None))),
+            QueueAction::Requeue(7)
+        );
+        assert_eq!(
+            feed_action(Some(&task(JobStatus::Queued, None))),
+            QueueAction::Skip
+        );
+        assert_eq!(
+            feed_action(Some(&task(JobStatus::Executing, None))),
+            QueueAction::Skip
+        );
+    }
+
+    #[test]
+    fn queues_new_feed_items() {
+        assert_eq!(item_action(None, None), QueueAction::Insert);
+        assert_eq!(item_action(None, Some(at(9))), QueueAction::Insert);
+    }
+
+    #[test]
+    fn requeues_items_published_after_their_crawl() {
+        // Without a stored publication date, the last crawl is what the item is compared to.
+        let crawled = task(JobStatus::Complete, None);
+        assert_eq!(
+            item_action(Some(&crawled), Some(at(13))),
+            QueueAction::Requeue(7)
+        );
+        assert_eq!(item_action(Some(&crawled), Some(at(11))), QueueAction::Skip);
+        assert_eq!(item_action(Some(&crawled), None), QueueAction::Skip);
+
+        let published = task(JobStatus::Complete, Some(at(6)));
+        assert_eq!(
+            item_action(Some(&published), Some(at(9))),
+            QueueAction::Requeue(7)
+        );
+        assert_eq!(
+            item_action(Some(&published), Some(at(6))),
+            QueueAction::Skip
+        );
+    }
+
+    #[test]
+    fn leaves_items_that_are_already_crawling() {
+        assert_eq!(
+            item_action(Some(&task(JobStatus::Executing, None)), Some(at(13))),
+            QueueAction::Skip
+        );
+        assert_eq!(
+            item_action(Some(&task(JobStatus::Queued, None)), Some(at(13))),
+            QueueAction::Skip
+        );
+    }
+}
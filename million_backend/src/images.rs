@@ -0,0 +1,408 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use entity::{image, image_source, websites};
+use proto::crawler::return_job_request;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, Condition, ConnectionTrait, DatabaseConnection,
+    EntityTrait, QueryFilter, QueryOrder, QuerySelect, TryInsertResult,
+};
+use sea_query::{Expr, OnConflict};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::thumbnail::ThumbnailStore;
+
+/// Query parameters that only track where a visitor came from.
+const TRACKING_PARAM_PREFIXES: &[&str] = &["utm_"];
+
+/// One resolution of an image, as stored in `image.variants`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Variant {
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub density: Option<f32>,
+}
+
+/// The pages an image was found on.
+#[derive(Debug, Clone)]
+pub struct ImageSources {
+    pub page_count: u32,
+    /// The most recently crawled page showing the image.
+    pub website: Option<websites::Model>,
+}
+
+/// Url used to recognise the same image across pages: no fragment, no tracking
+/// parameters and the remaining query parameters sorted.
+pub fn normalize_image_url(url: &str) -> anyhow::Result<String> {
+    let mut url = url.parse::<Url>()?;
+    url.set_fragment(None);
+
+    let mut query = url
+        .query_pairs()
+        .filter(|(key, _)| {
+            !TRACKING_PARAM_PREFIXES
+                .iter()
+                .any(|prefix| key.starts_with(prefix))
+        })
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect::<Vec<_>>();
+    query.sort();
+
+    if query.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(query);
+    }
+
+    Ok(url.to_string())
+}
+
+/// Url of the largest resolution among an image's stored variants, judged by width and
+/// then by pixel density. Variants that give neither are skipped.
+pub fn largest_variant(variants: &serde_json::Value) -> Option<String> {
+    serde_json::from_value::<Vec<Variant>>(variants.clone())
+        .ok()?
+        .into_iter()
+        .filter(|variant| variant.width.is_some() || variant.density.is_some())
+        .max_by(|a, b| {
+            a.width.cmp(&b.width).then(
+                a.density
+                    .unwrap_or(1.0)
+                    .total_cmp(&b.density.unwrap_or(1.0)),
+            )
+        })
+        .map(|variant| variant.url)
+}
+
+/// Links a crawled image to the page it was found on, reusing the canonical record for
+/// an image already seen under the same normalized url, under the url of one of its
+/// other resolutions, or with the same content.
+pub async fn store_image(
+    db: &DatabaseConnection,
+    thumbnails: &ThumbnailStore,
+    website_id: i32,
+    img: return_job_request::ok::body::Image,
+) -> anyhow::Result<()> {
+    let normalized_url = normalize_image_url(&img.image_url)?;
+    let context = img.context.and_then(context_text);
+    let content_hash = (!img.content_hash.is_empty()).then_some(img.content_hash);
+
+    let variants = img
+        .variants
+        .into_iter()
+        .map(|variant| Variant {
+            url: variant.url,
+            width: variant.width,
+            density: variant.density,
+        })
+        .collect::<Vec<_>>();
+
+    let mut known_urls = vec![normalized_url.clone()];
+    for variant in &variants {
+        let url = normalize_image_url(&variant.url)?;
+        if !known_urls.contains(&url) {
+            known_urls.push(url);
+        }
+    }
+
+    let mut condition = Condition::any().add(image::Column::NormalizedUrl.is_in(known_urls));
+    if let Some(content_hash) = &content_hash {
+        condition = condition.add(image::Column::ContentHash.eq(content_hash.clone()));
+    }
+
+    let existing = image::Entity::find()
+        .filter(condition)
+        .order_by_asc(image::Column::Id)
+        .one(db)
+        .await?;
+
+    let image_id = match existing {
+        Some(existing) => {
+            merge_image(
+                db,
+                thumbnails,
+                existing,
+                img.alt_text.clone(),
+                context,
+                variants,
+                img.thumbnail,
+            )
+            .await?
+        }
+        None => {
+            let (width, height) = img
+                .size
+                .map(|size| (Some(size.width), Some(size.height)))
+                .unwrap_or_default();
+
+            let (min_luminance, max_luminance, mean_luminance) = img
+                .luminance_range
+                .map(|range| (Some(range.min), Some(range.max), Some(range.mean)))
+                .unwrap_or_default();
+
+            let inserted = image::Entity::insert(image::ActiveModel {
+                url: ActiveValue::Set(img.image_url),
+                normalized_url: ActiveValue::Set(normalized_url.clone()),
+                content_hash: ActiveValue::Set(content_hash),
+                width: ActiveValue::Set(width),
+                height: ActiveValue::Set(height),
+                alt_text: ActiveValue::Set(img.alt_text.clone()),
+                context: ActiveValue::Set(context.clone()),
+                variants: ActiveValue::Set(serde_json::to_value(&variants)?),
+                perceptual_hash: ActiveValue::Set(img.perceptual_hash.map(|hash| hash as i64)),
+                min_luminance: ActiveValue::Set(min_luminance),
+                max_luminance: ActiveValue::Set(max_luminance),
+                mean_luminance: ActiveValue::Set(mean_luminance),
+                dominant_colors: ActiveValue::Set(img.dominant_colors),
+                has_thumbnail: ActiveValue::Set(img.thumbnail.is_some()),
+                ..Default::default()
+            })
+            .on_conflict(
+                OnConflict::column(image::Column::NormalizedUrl)
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .do_nothing()
+            .exec(db)
+            .await?;
+
+            match inserted {
+                TryInsertResult::Inserted(inserted) => {
+                    if let Some(thumbnail) = img.thumbnail {
+                        thumbnails.put(inserted.last_insert_id, thumbnail).await?;
+                    }
+
+                    inserted.last_insert_id
+                }
+                // Another worker stored an image under the same url since the lookup
+                // above, so this one becomes a page of that image.
+                TryInsertResult::Conflicted | TryInsertResult::Empty => {
+                    let existing = image::Entity::find()
+                        .filter(image::Column::NormalizedUrl.eq(normalized_url.as_str()))
+                        .one(db)
+                        .await?
+                        .ok_or_else(|| {
+                            anyhow!("image {} was deleted while storing", normalized_url)
+                        })?;
+
+                    merge_image(
+                        db,
+                        thumbnails,
+                        existing,
+                        img.alt_text.clone(),
+                        context,
+                        variants,
+                        img.thumbnail,
+                    )
+                    .await?
+                }
+            }
+        }
+    };
+
+    image_source::Entity::insert(image_source::ActiveModel {
+        image_id: ActiveValue::Set(image_id),
+        website_id: ActiveValue::Set(website_id),
+        alt_text: ActiveValue::Set(img.alt_text),
+        ..Default::default()
+    })
+    .on_conflict(
+        OnConflict::columns([
+            image_source::Column::ImageId,
+            image_source::Column::WebsiteId,
+        ])
+        .do_nothing()
+        .to_owned(),
+    )
+    .exec_without_returning(db)
+    .await?;
+
+    Ok(())
+}
+
+/// Fills in what the stored record of an image lacks from another sighting of it, and
+/// returns its id.
+async fn merge_image(
+    db: &DatabaseConnection,
+    thumbnails: &ThumbnailStore,
+    existing: image::Model,
+    alt_text: Option<String>,
+    context: Option<String>,
+    variants: Vec<Variant>,
+    thumbnail: Option<Vec<u8>>,
+) -> anyhow::Result<i32> {
+    let mut image = image::ActiveModel {
+        id: ActiveValue::Unchanged(existing.id),
+        ..Default::default()
+    };
+
+    if existing.alt_text.is_none() && alt_text.is_some() {
+        image.alt_text = ActiveValue::Set(alt_text);
+    }
+
+    if existing.context.is_none() && context.is_some() {
+        image.context = ActiveValue::Set(context);
+    }
+
+    let mut known_variants =
+        serde_json::from_value::<Vec<Variant>>(existing.variants).unwrap_or_default();
+    let known_count = known_variants.len();
+
+    for variant in variants {
+        if !known_variants.iter().any(|known| known.url == variant.url) {
+            known_variants.push(variant);
+        }
+    }
+
+    if known_variants.len() != known_count {
+        image.variants = ActiveValue::Set(serde_json::to_value(known_variants)?);
+    }
+
+    if !existing.has_thumbnail {
+        if let Some(thumbnail) = thumbnail {
+            thumbnails.put(existing.id, thumbnail).await?;
+            image.has_thumbnail = ActiveValue::Set(true);
+        }
+    }
+
+    if image.is_changed() {
+        image.update(db).await?;
+    }
+
+    Ok(existing.id)
+}
+
+/// Joins the text found around an image into one searchable field.
+fn context_text(context: return_job_request::ok::body::image::Context) -> Option<String> {
+    let filename = (!context.filename_tokens.is_empty()).then(|| context.filename_tokens.join(" "));
+
+    let text = [
+        context.caption,
+        context.title,
+        context.heading,
+        context.paragraph,
+        filename,
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>()
+    .join("\n");
+
+    (!text.is_empty()).then_some(text)
+}
+
+/// Removes the links from the given pages to their images, deleting images (and their
+/// thumbnails) that are no longer found on any page.
+pub async fn unlink_images(
+    db: &DatabaseConnection,
+    thumbnails: &ThumbnailStore,
+    website_ids: Vec<i32>,
+) -> anyhow::Result<()> {
+    let image_ids = image_source::Entity::find()
+        .select_only()
+        .column(image_source::Column::ImageId)
+        .filter(image_source::Column::WebsiteId.is_in(website_ids.clone()))
+        .into_tuple::<i32>()
+        .all(db)
+        .await?;
+
+    image_source::Entity::delete_many()
+        .filter(image_source::Column::WebsiteId.is_in(website_ids))
+        .exec(db)
+        .await?;
+
+    // Checking for sources in the delete itself keeps an image that another page
+    // linked in the meantime.
+    let delete = sea_query::Query::delete()
+        .from_table(image::Entity)
+        .and_where(image::Column::Id.is_in(image_ids))
+        .and_where(
+            Expr::exists(
+                sea_query::Query::select()
+                    .expr(Expr::val(1))
+                    .from(image_source::Entity)
+                    .and_where(
+                        Expr::col((image_source::Entity, image_source::Column::ImageId))
+                            .equals((image::Entity, image::Column::Id)),
+                    )
+                    .to_owned(),
+            )
+            .not(),
+        )
+        .returning_all()
+        .to_owned();
+
+    let orphans = image::Entity::find()
+        .from_raw_sql(db.get_database_backend().build(&delete))
+        .all(db)
+        .await?;
+
+    for orphan in orphans.iter().filter(|orphan| orphan.has_thumbnail) {
+        thumbnails.delete(orphan.id).await?;
+    }
+
+    Ok(())
+}
+
+/// Page counts and the latest page for each of the given images.
+pub async fn image_sources(
+    db: &DatabaseConnection,
+    image_ids: &[i32],
+) -> anyhow::Result<HashMap<i32, ImageSources>> {
+    let counts = image_source::Entity::find()
+        .select_only()
+        .column(image_source::Column::ImageId)
+        .column_as(
+            Expr::col(image_source::Column::WebsiteId).count(),
+            "page_count",
+        )
+        .filter(image_source::Column::ImageId.is_in(image_ids.iter().copied()))
+        .group_by(image_source::Column::ImageId)
+        .into_tuple::<(i32, i64)>()
+        .all(db)
+        .await?;
+
+    // Links are recreated whenever a page is crawled again, so the newest link points at
+    // the page most recently seen with the image.
+    let latest = image_source::Entity::find()
+        .select_only()
+        .column(image_source::Column::ImageId)
+        .column(image_source::Column::WebsiteId)
+        .distinct_on([image_source::Column::ImageId])
+        .filter(image_source::Column::ImageId.is_in(image_ids.iter().copied()))
+        .order_by_asc(image_source::Column::ImageId)
+        .order_by_desc(image_source::Column::CreatedAt)
+        .order_by_desc(image_source::Column::WebsiteId)
+        .into_tuple::<(i32, i32)>()
+        .all(db)
+        .await?
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+
+    let websites = websites::Entity::find()
+        .filter(websites::Column::Id.is_in(latest.values().copied()))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|website| (website.id, website))
+        .collect::<HashMap<_, _>>();
+
+    Ok(counts
+        .into_iter()
+        .map(|(image_id, page_count)| {
+            (
+                image_id,
+                ImageSources {
+                    page_count: page_count as u32,
+                    website: latest
+                        .get(&image_id)
+                        .and_then(|website_id| websites.get(website_id))
+                        .cloned(),
+                },
+            )
+        })
+        .collect())
+}
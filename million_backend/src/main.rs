@@ -8,12 +8,23 @@ use migration::{Migrator, MigratorTrait};
 use proto::tonic::{codec::CompressionEncoding, transport::Server};
 use sea_orm::Database;
 use search::SearchServise;
+use thumbnail::ThumbnailStore;
 use tracing_subscriber::EnvFilter;
 use url::Url;
 
 mod admin;
 mod crawler;
+mod feed;
+mod images;
+mod rich_result;
 mod search;
+mod thumbnail;
+
+/// Largest search request accepted, large enough for reverse image search uploads.
+const MAX_MESSAGE_BYTES: usize = 16 * 1024 * 1024;
+
+/// Largest crawl result accepted, large enough for a page's image thumbnails.
+const MAX_JOB_MESSAGE_BYTES: usize = 64 * 1024 * 1024;
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
@@ -29,6 +40,15 @@ struct Args {
 
     #[arg(short, long, env, default_value_t = 8080)]
     port: u16,
+
+    /// Minutes between polls of each registered RSS/Atom feed.
+    #[arg(long, env, default_value_t = 60)]
+    feed_poll_minutes: i64,
+
+    /// Where image thumbnails are stored: a `file://` directory or an `s3://bucket/prefix`
+    /// configured through the `AWS_*` environment variables.
+    #[arg(long, env, default_value = "file:///var/lib/million_search/thumbnails")]
+    thumbnail_store: Url,
 }
 
 #[tokio::main]
@@ -44,6 +64,8 @@ async fn main() -> anyhow::Result<()> {
 
     Migrator::up(&db, None).await?;
 
+    let thumbnails = ThumbnailStore::new(&args.thumbnail_store)?;
+
     // Connect to meilisearch
     let search_client = Client::new(args.meilisearch_url, Option::<String>::None)?;
 
@@ -60,12 +82,34 @@ async fn main() -> anyhow::Result<()> {
             "site_short_name",
             "site_description",
             "site_categories",
+            "anchor_texts",
+            "author",
         ])
         .await?;
 
+    search_client
+        .index("websites")
+        .set_filterable_attributes(["language", "document_type"])
+        .await?;
+
     search_client
         .index("image")
-        .set_searchable_attributes(["url", "alt_text", "source_url"])
+        .set_searchable_attributes(["url", "alt_text", "context", "source_url"])
+        .await?;
+
+    search_client
+        .index("image")
+        .set_filterable_attributes(["dominant_colors", "mean_luminance"])
+        .await?;
+
+    search_client
+        .index("video")
+        .set_searchable_attributes(["title", "url"])
+        .await?;
+
+    search_client
+        .index("audio")
+        .set_searchable_attributes(["title", "url"])
         .await?;
 
     search_client
@@ -92,13 +136,15 @@ async fn main() -> anyhow::Result<()> {
 
     let addr = SocketAddr::new(args.host_address, args.port);
 
-    let search_servise = SearchServise {
-        db: db.clone(),
-        search_client: search_client.clone(),
-    };
+    let search_servise = SearchServise::new(db.clone(), search_client, thumbnails.clone())?;
+    let feed_poll_interval = chrono::Duration::minutes(args.feed_poll_minutes);
+
+    tokio::spawn(feed::poll_feeds(db.clone(), feed_poll_interval));
+
     let crawler_servise = CrawlerServise {
         db: db.clone(),
-        search_client,
+        feed_poll_interval,
+        thumbnails,
     };
     let admin_servise = AdminServise { db };
 
@@ -107,11 +153,13 @@ async fn main() -> anyhow::Result<()> {
     Server::builder()
         .add_service(
             proto::search::search_server::SearchServer::new(search_servise)
+                .max_decoding_message_size(MAX_MESSAGE_BYTES)
                 .send_compressed(CompressionEncoding::Zstd)
                 .accept_compressed(CompressionEncoding::Zstd),
         )
         .add_service(
             proto::crawler::crawler_server::CrawlerServer::new(crawler_servise)
+                .max_decoding_message_size(MAX_JOB_MESSAGE_BYTES)
                 .send_compressed(CompressionEncoding::Zstd)
                 .accept_compressed(CompressionEncoding::Zstd),
         )
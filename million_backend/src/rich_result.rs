@@ -0,0 +1,434 @@
+use proto::search::{
+    faq_result::Question, search_web_result::RichResult, ArticleResult, EventResult, FaqResult,
+    ProductResult, Rating, RecipeResult,
+};
+use serde_json::Value;
+
+const ARTICLE_TYPES: &[&str] = &[
+    "Article",
+    "NewsArticle",
+    "BlogPosting",
+    "TechArticle",
+    "ScholarlyArticle",
+    "Report",
+];
+
+/// Maximum number of FAQ entries shown under a result.
+const MAX_FAQ_QUESTIONS: usize = 3;
+
+/// Picks the most specific schema.org node in a page's JSON-LD blocks and turns it into
+/// a rich result. Recipes, products, events and FAQs win over plain articles since most
+/// CMSs emit an `Article` node for every page.
+pub fn from_json_ld(json_ld: &Value) -> Option<RichResult> {
+    let mut nodes = Vec::new();
+    collect_nodes(json_ld, &mut nodes);
+
+    let find = |types: &[&str]| nodes.iter().copied().find(|node| has_type(node, types));
+
+    find(&["Recipe"])
+        .map(recipe)
+        .or_else(|| find(&["Product"]).map(product))
+        .or_else(|| nodes.iter().copied().find(|node| is_event(node)).map(event))
+        .or_else(|| find(&["FAQPage", "QAPage"]).and_then(faq))
+        .or_else(|| find(ARTICLE_TYPES).map(article))
+}
+
+/// Typed nodes in document order, including those in a `@graph` and the `mainEntity` of
+/// another node, where pages often put their `FAQPage` or `QAPage` under a `WebPage`.
+fn collect_nodes<'a>(value: &'a Value, nodes: &mut Vec<&'a Value>) {
+    match value {
+        Value::Array(values) => values.iter().for_each(|value| collect_nodes(value, nodes)),
+        Value::Object(object) => {
+            if object.contains_key("@type") {
+                nodes.push(value);
+            }
+            for key in ["@graph", "mainEntity"] {
+                if let Some(nested) = object.get(key) {
+                    collect_nodes(nested, nodes);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn types(node: &Value) -> impl Iterator<Item = &str> {
+    let types = match node.get("@type") {
+        Some(Value::Array(types)) => types.iter().filter_map(Value::as_str).collect(),
+        Some(Value::String(ty)) => vec![ty.as_str()],
+        _ => vec![],
+    };
+
+    types
+        .into_iter()
+        .map(|ty| ty.rsplit(['/', ':']).next().unwrap_or(ty))
+}
+
+fn has_type(node: &Value, wanted: &[&str]) -> bool {
+    types(node).any(|ty| wanted.contains(&ty))
+}
+
+fn is_event(node: &Value) -> bool {
+    types(node).any(|ty| ty == "Event" || (ty.ends_with("Event") && ty != "PublicationEvent"))
+}
+
+/// Plain text of a value: strings and numbers as-is, objects by their `name`, arrays by
+/// their first usable element.
+fn text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(strip_tags(text)).filter(|text| !text.is_empty()),
+        Value::Number(number) => Some(number.to_string()),
+        Value::Object(object) => object.get("name").and_then(text),
+        Value::Array(values) => values.iter().find_map(text),
+        _ => None,
+    }
+}
+
+fn field(node: &Value, key: &str) -> Option<String> {
+    node.get(key).and_then(text)
+}
+
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(number) => number.as_f64(),
+        Value::String(number) => number.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn strip_tags(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn rating(node: &Value) -> Option<Rating> {
+    let rating = node.get("aggregateRating")?;
+
+    Some(Rating {
+        value: rating.get("ratingValue").and_then(number)?,
+        count: rating
+            .get("ratingCount")
+            .or_else(|| rating.get("reviewCount"))
+            .and_then(number)
+            .map(|count| count as u32),
+    })
+}
+
+/// Formats an ISO 8601 duration such as `PT1H30M` as `1 h 30 min`.
+fn duration(value: &str) -> Option<String> {
+    let time = value.trim().strip_prefix("P")?;
+    let (days, time) = time.split_once('T').unwrap_or((time, ""));
+
+    let mut parts = Vec::new();
+    let mut push = |units: &str, suffixes: &[(char, &str)]| -> Option<()> {
+        let mut amount = String::new();
+        for c in units.chars() {
+            if c.is_ascii_digit() || c == '.' {
+                amount.push(c);
+            } else {
+                let (_, suffix) = suffixes.iter().find(|(unit, _)| *unit == c)?;
+                if amount.parse::<f64>().ok()? > 0.0 {
+                    parts.push(format!("{} {}", amount, suffix));
+                }
+                amount.clear();
+            }
+        }
+        amount.is_empty().then_some(())
+    };
+
+    push(days, &[('Y', "y"), ('M', "mo"), ('W', "wk"), ('D', "d")])?;
+    push(time, &[('H', "h"), ('M', "min"), ('S', "s")])?;
+
+    (!parts.is_empty()).then(|| parts.join(" "))
+}
+
+fn article(node: &Value) -> RichResult {
+    let authors = match node.get("author") {
+        Some(Value::Array(authors)) => authors.iter().filter_map(text).collect(),
+        Some(author) => text(author).into_iter().collect(),
+        None => vec![],
+    };
+
+    RichResult::Article(ArticleResult {
+        authors,
+        date_published: field(node, "datePublished"),
+        publisher: field(node, "publisher"),
+    })
+}
+
+fn recipe(node: &Value) -> RichResult {
+    RichResult::Recipe(RecipeResult {
+        total_time: field(node, "totalTime")
+            .or_else(|| field(node, "cookTime"))
+            .map(|time| duration(&time).unwrap_or(time)),
+        recipe_yield: field(node, "recipeYield"),
+        rating: rating(node),
+        calories: node
+            .get("nutrition")
+            .and_then(|nutrition| field(nutrition, "calories")),
+    })
+}
+
+fn product(node: &Value) -> RichResult {
+    let offer = match node.get("offers") {
+        Some(Value::Array(offers)) => offers.first(),
+        offer => offer,
+    };
+
+    RichResult::Product(ProductResult {
+        price: offer.and_then(|offer| field(offer, "price").or_else(|| field(offer, "lowPrice"))),
+        currency: offer.and_then(|offer| field(offer, "priceCurrency")),
+        availability: offer
+            .and_then(|offer| field(offer, "availability"))
+            .map(|availability| {
+                availability
+                    .rsplit('/')
+                    .next()
+                    .unwrap_or(&availability)
+                    .to_owned()
+            }),
+        brand: field(node, "brand"),
+        rating: rating(node),
+    })
+}
+
+fn event(node: &Value) -> RichResult {
+    let location = node.get("location").and_then(|location| {
+        let name = field(location, "name");
+        let address = location.get("address").and_then(|address| match address {
+            Value::Object(_) => {
+                let parts = ["streetAddress", "addressLocality", "addressCountry"]
+                    .iter()
+                    .filter_map(|key| field(address, key))
+                    .collect::<Vec<_>>();
+                (!parts.is_empty()).then(|| parts.join(", "))
+            }
+            address => text(address),
+        });
+
+        match (name, address) {
+            (Some(name), Some(address)) => Some(format!("{}, {}", name, address)),
+            (name, address) => name.or(address).or_else(|| text(location)),
+        }
+    });
+
+    RichResult::Event(EventResult {
+        start_date: field(node, "startDate"),
+        end_date: field(node, "endDate"),
+        location,
+    })
+}
+
+fn faq(node: &Value) -> Option<RichResult> {
+    let questions = match node.get("mainEntity")? {
+        Value::Array(questions) => questions.iter().collect(),
+        question => vec![question],
+    };
+
+    // A `QAPage` has one question, whose answer may only be suggested.
+    let answer = |question: &Value| {
+        ["acceptedAnswer", "suggestedAnswer"]
+            .into_iter()
+            .filter_map(|key| question.get(key))
+            .flat_map(|answers| match answers {
+                Value::Array(answers) => answers.iter().collect(),
+                answer => vec![answer],
+            })
+            .find_map(|answer| field(answer, "text"))
+    };
+
+    let questions = questions
+        .into_iter()
+        .filter_map(|question| {
+            Some(Question {
+                question: field(question, "name")?,
+                answer: answer(question)?,
+            })
+        })
+        .take(MAX_FAQ_QUESTIONS)
+        .collect::<Vec<_>>();
+
+    (!questions.is_empty()).then_some(RichResult::Faq(FaqResult { questions }))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn prefers_recipes_over_articles_in_a_graph() {
+        let json_ld = json!({
+            "@context": "https://schema.org",
+            "@graph": [
+                {
+                    "@type": "Article",
+                    "author": { "@type": "Person", "name": "Ada" },
+                },
+                {
+                    "@type": "Recipe",
+                    "name": "Pancakes",
+                    "totalTime": "PT1H30M",
+                    "recipeYield": ["4", "4 servings"],
+                    "nutrition": { "@type": "NutritionInformation", "calories": "320 kcal" },
+                    "aggregateRating": { "ratingValue": "4.5", "ratingCount": 12 },
+                },
+            ],
+        });
+
+        assert_eq!(
+            from_json_ld(&json_ld),
+            Some(RichResult::Recipe(RecipeResult {
+                total_time: Some(String::from("1 h 30 min")),
+                recipe_yield: Some(String::from("4")),
+                rating: Some(Rating {
+                    value: 4.5,
+                    count: Some(12),
+                }),
+                calories: Some(String::from("320 kcal")),
+            }))
+        );
+    }
+
+    #[test]
+    fn reads_products_events_and_articles() {
+        let product = json!({
+            "@type": "http://schema.org/Product",
+            "brand": { "@type": "Brand", "name": "Acme" },
+            "offers": [
+                {
+                    "@type": "Offer",
+                    "price": 19.99,
+                    "priceCurrency": "EUR",
+                    "availability": "https://schema.org/InStock",
+                },
+            ],
+        });
+        assert_eq!(
+            from_json_ld(&product),
+            Some(RichResult::Product(ProductResult {
+                price: Some(String::from("19.99")),
+                currency: Some(String::from("EUR")),
+                availability: Some(String::from("InStock")),
+                brand: Some(String::from("Acme")),
+                rating: None,
+            }))
+        );
+
+        let event = json!([{
+            "@type": "MusicEvent",
+            "startDate": "2024-07-01T20:00",
+            "location": {
+                "@type": "Place",
+                "name": "Town Hall",
+                "address": { "addressLocality": "Leeds", "addressCountry": "GB" },
+            },
+        }]);
+        assert_eq!(
+            from_json_ld(&event),
+            Some(RichResult::Event(EventResult {
+                start_date: Some(String::from("2024-07-01T20:00")),
+                end_date: None,
+                location: Some(String::from("Town Hall, Leeds, GB")),
+            }))
+        );
+
+        let article = json!({
+            "@type": ["NewsArticle"],
+            "author": [{ "name": "Ada" }, "Grace"],
+            "datePublished": "2024-05-01",
+            "publisher": { "@type": "Organization", "name": "<b>Daily</b> News" },
+        });
+        assert_eq!(
+            from_json_ld(&article),
+            Some(RichResult::Article(ArticleResult {
+                authors: vec![String::from("Ada"), String::from("Grace")],
+                date_published: Some(String::from("2024-05-01")),
+                publisher: Some(String::from("Daily News")),
+            }))
+        );
+    }
+
+    #[test]
+    fn finds_faq_under_a_web_page() {
+        let json_ld = json!({
+            "@type": "WebPage",
+            "mainEntity": {
+                "@type": "FAQPage",
+                "mainEntity": [
+                    {
+                        "@type": "Question",
+                        "name": "Is it free?",
+                        "acceptedAnswer": { "@type": "Answer", "text": "<p>Yes.</p>" },
+                    },
+                    { "@type": "Question", "name": "Unanswered" },
+                ],
+            },
+        });
+
+        assert_eq!(
+            from_json_ld(&json_ld),
+            Some(RichResult::Faq(FaqResult {
+                questions: vec![Question {
+                    question: String::from("Is it free?"),
+                    answer: String::from("Yes."),
+                }],
+            }))
+        );
+    }
+
+    #[test]
+    fn reads_suggested_answers_of_a_qa_page() {
+        let json_ld = json!({
+            "@type": "QAPage",
+            "mainEntity": {
+                "@type": "Question",
+                "name": "How do I exit vim?",
+                "suggestedAnswer": [
+                    { "@type": "Answer", "text": ":q" },
+                    { "@type": "Answer", "text": ":wq" },
+                ],
+            },
+        });
+
+        assert_eq!(
+            from_json_ld(&json_ld),
+            Some(RichResult::Faq(FaqResult {
+                questions: vec![Question {
+                    question: String::from("How do I exit vim?"),
+                    answer: String::from(":q"),
+                }],
+            }))
+        );
+    }
+
+    #[test]
+    fn ignores_untyped_and_unknown_nodes() {
+        assert_eq!(from_json_ld(&json!({ "name": "No type" })), None);
+        assert_eq!(from_json_ld(&json!({ "@type": "Organization" })), None);
+        assert_eq!(from_json_ld(&json!({ "@type": "FAQPage" })), None);
+    }
+
+    #[test]
+    fn formats_durations() {
+        assert_eq!(duration("PT45M").as_deref(), Some("45 min"));
+        assert_eq!(duration("P1DT2H").as_deref(), Some("1 d 2 h"));
+        assert_eq!(duration("PT0M"), None);
+        assert_eq!(duration("45 minutes"), None);
+    }
+}
@@ -1,34 +1,105 @@
+use std::{collections::HashMap, io::Cursor, time::Duration};
+
 use chrono::Utc;
-use entity::{search_history, websites};
+use entity::{sea_orm_active_enums::DocumentType, search_history, websites};
 use futures::future::join_all;
 use meilisearch_sdk::{client::Client, search::SearchResults};
 use migration::OnConflict;
+use million_common::{
+    image_hash,
+    net::{read_capped, AddressFilter},
+};
 use proto::{
+    prost::bytes::Bytes,
     search::{
-        search_image_result::Size, CompleteSearchRequest, CompleteSearchResponse,
-        SearchImageRequest, SearchImageResponse, SearchImageResult, SearchWebRequest,
-        SearchWebResponse, SearchWebResult,
+        search_image_by_similarity_request, search_image_result::Size, Brightness,
+        CompleteSearchRequest, CompleteSearchResponse, GetImageRequest, GetImageResponse,
+        SearchAudioRequest, SearchAudioResponse, SearchAudioResult, SearchImageBySimilarityRequest,
+        SearchImageRequest, SearchImageResponse, SearchImageResult, SearchVideoRequest,
+        SearchVideoResponse, SearchVideoResult, SearchWebRequest, SearchWebResponse,
+        SearchWebResult,
     },
     tonic::{self, Response, Status},
 };
-use sea_orm::{DatabaseConnection, EntityTrait};
+use sea_orm::{
+    ActiveEnum, ColumnTrait, DatabaseConnection, EntityTrait, Order, QueryFilter, QueryOrder,
+    QuerySelect,
+};
 use sea_query::Expr;
 use serde::{Deserialize, Serialize};
+use tokio::task::spawn_blocking;
+use url::Url;
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct WebsiteSearch {
-    pub id: i64,
-    pub url: String,
-    pub title: Option<String>,
-    pub description: Option<String>,
-    pub text_fields: Vec<String>,
-    pub sections: Vec<String>,
-}
+use crate::{
+    images::{image_sources, largest_variant, normalize_image_url},
+    rich_result,
+    thumbnail::ThumbnailStore,
+};
+
+/// Largest Hamming distance between perceptual hashes still shown as similar.
+const MAX_HASH_DISTANCE: i32 = 10;
+
+const SIMILAR_IMAGES_PER_PAGE: u64 = 20;
+
+/// Limits for images fetched for reverse image search. The URL comes from an anonymous
+/// user, so the fetch is kept short, small and away from internal addresses.
+const SIMILAR_FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+const SIMILAR_FETCH_MAX_REDIRECTS: usize = 5;
+const MAX_SIMILAR_IMAGE_BYTES: usize = 16 * 1024 * 1024;
+
+/// Decoder limits for searched images, so a decompression bomb fails instead of
+/// allocating gigabytes.
+const MAX_SIMILAR_IMAGE_DIMENSION: u32 = 10_000;
+const MAX_SIMILAR_IMAGE_ALLOC_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Mean relative luminance at or below which an image counts as dark.
+const DARK_MAX_LUMINANCE: f32 = 0.1;
+
+/// Mean relative luminance at or above which an image counts as light.
+const LIGHT_MIN_LUMINANCE: f32 = 0.4;
 
 #[derive(Debug)]
 pub struct SearchServise {
     pub db: DatabaseConnection,
     pub search_client: Client,
+    pub thumbnails: ThumbnailStore,
+    /// Fetches images for reverse image search, only from public addresses.
+    pub http_client: reqwest::Client,
+}
+
+impl SearchServise {
+    pub fn new(
+        db: DatabaseConnection,
+        search_client: Client,
+        thumbnails: ThumbnailStore,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            db,
+            search_client,
+            thumbnails,
+            http_client: AddressFilter::default()
+                .client(SIMILAR_FETCH_TIMEOUT, SIMILAR_FETCH_MAX_REDIRECTS)?,
+        })
+    }
+
+    /// Downloads an image a user asked to search by. Failures are logged but not passed
+    /// on, so the endpoint can't be used to probe which hosts and ports answer.
+    async fn fetch_image(&self, url: &str) -> Result<Bytes, Status> {
+        let fetch = async {
+            let res = self
+                .http_client
+                .get(url.parse::<Url>()?)
+                .send()
+                .await?
+                .error_for_status()?;
+            read_capped(res, MAX_SIMILAR_IMAGE_BYTES).await
+        };
+
+        fetch.await.map(Bytes::from).map_err(|err| {
+            tracing::debug!("fetching {} for similarity search failed: {}", url, err);
+            Status::invalid_argument("could not fetch image")
+        })
+    }
 }
 
 #[tonic::async_trait]
@@ -73,12 +144,49 @@ impl proto::search::search_server::Search for SearchServise {
             .await
             .map_err(|err| Status::from_error(err.into()))?;
 
-        let result: SearchResults<Websites> = self
-            .search_client
-            .index("websites")
-            .search()
-            .with_query(&query.query)
-            .with_page(query.page as usize)
+        for language in [&request.language, &request.preferred_language]
+            .into_iter()
+            .flatten()
+        {
+            if !language
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-')
+            {
+                return Err(Status::invalid_argument("invalid language"));
+            }
+        }
+
+        let (text, document_type) = split_filetype(&query.query);
+
+        let language_filter = match (request.language, request.preferred_language) {
+            (Some(language), _) => Some(format!("language = \"{}\"", language)),
+            // Most pages have no detected language, including everything crawled before
+            // detection existed, so a preference must not hide them.
+            (None, Some(language)) => Some(format!(
+                "(language = \"{}\" OR language IS NULL OR language NOT EXISTS)",
+                language
+            )),
+            (None, None) => None,
+        };
+
+        let filters =
+            language_filter
+                .into_iter()
+                .chain(document_type.map(|document_type| {
+                    format!("document_type = \"{}\"", document_type.to_value())
+                }))
+                .collect::<Vec<_>>()
+                .join(" AND ");
+
+        let index = self.search_client.index("websites");
+        let mut search = index.search();
+        search.with_query(&text).with_page(query.page as usize);
+
+        if !filters.is_empty() {
+            search.with_filter(&filters);
+        }
+
+        let result: SearchResults<Websites> = search
             .execute()
             .await
             .map_err(|err| Status::from_error(err.into()))?;
@@ -95,19 +203,11 @@ impl proto::search::search_server::Search for SearchServise {
         .map_err(|err| Status::from_error(err.into()))?
         .ok_or(Status::internal("desync between postgres and meiliseach"))?;
 
-        let results = list
-            .into_iter()
-            .map(|model| SearchWebResult {
-                // id: model.id,
-                url: model.url,
-                title: model.title,
-                description: model.description,
-                icon_url: model.icon_url,
-                inner_text_match: None,
-                site_name: model.site_name,
-                site_description: model.site_description,
-            })
-            .collect::<Vec<_>>();
+        let mut results = list.into_iter().map(website_to_result).collect::<Vec<_>>();
+
+        attach_image_ids(&self.db, &mut results)
+            .await
+            .map_err(|err| Status::from_error(err.into()))?;
 
         Ok(Response::new(SearchWebResponse { results }))
     }
@@ -116,6 +216,143 @@ impl proto::search::search_server::Search for SearchServise {
         &self,
         request: tonic::Request<SearchImageRequest>,
     ) -> std::result::Result<tonic::Response<SearchImageResponse>, tonic::Status> {
+        let request = request.into_inner();
+        let brightness = request.brightness();
+        let query = request
+            .query
+            .ok_or(Status::invalid_argument("must have query"))?;
+
+        save_search_to_history(&self.db, &query.query)
+            .await
+            .map_err(|err| Status::from_error(err.into()))?;
+
+        if let Some(color) = &request.color {
+            if !color.chars().all(|c| c.is_ascii_lowercase()) {
+                return Err(Status::invalid_argument("invalid color"));
+            }
+        }
+
+        let filters = request
+            .color
+            .as_ref()
+            .map(|color| format!("dominant_colors = \"{}\"", color))
+            .into_iter()
+            .chain(match brightness {
+                Brightness::Any => None,
+                Brightness::Dark => Some(format!("mean_luminance <= {}", DARK_MAX_LUMINANCE)),
+                Brightness::Light => Some(format!("mean_luminance >= {}", LIGHT_MIN_LUMINANCE)),
+            })
+            .collect::<Vec<_>>()
+            .join(" AND ");
+
+        let index = self.search_client.index("image");
+        let mut search = index.search();
+        search
+            .with_query(&query.query)
+            .with_page(query.page as usize);
+
+        if !filters.is_empty() {
+            search.with_filter(&filters);
+        }
+
+        let result: SearchResults<Image> = search
+            .execute()
+            .await
+            .map_err(|err| Status::from_error(err.into()))?;
+
+        let list =
+            join_all(result.hits.iter().map(|image| {
+                entity::image::Entity::find_by_id(image.result.id as i32).one(&self.db)
+            }))
+            .await
+            .into_iter()
+            .collect::<Result<Option<Vec<_>>, _>>()
+            .map_err(|err| Status::from_error(err.into()))?
+            .ok_or(Status::internal("desync between postgres and meiliseach"))?;
+
+        let results = images_to_results(&self.db, list)
+            .await
+            .map_err(|err| Status::from_error(err.into()))?;
+
+        Ok(Response::new(SearchImageResponse {
+            results,
+            query_image: None,
+        }))
+    }
+
+    async fn search_image_by_similarity(
+        &self,
+        request: tonic::Request<SearchImageBySimilarityRequest>,
+    ) -> std::result::Result<tonic::Response<SearchImageResponse>, tonic::Status> {
+        let request = request.into_inner();
+
+        let (hash, query_image, query_url) = match request
+            .image
+            .ok_or(Status::invalid_argument("must have image"))?
+        {
+            search_image_by_similarity_request::Image::Data(data) => {
+                (hash_image(data.into()).await?, None, None)
+            }
+            search_image_by_similarity_request::Image::Url(url) => {
+                let known_image = entity::image::Entity::find()
+                    .filter(entity::image::Column::Url.eq(&url))
+                    .filter(entity::image::Column::PerceptualHash.is_not_null())
+                    .one(&self.db)
+                    .await
+                    .map_err(|err| Status::from_error(err.into()))?;
+
+                let hash = match known_image.as_ref().and_then(|image| image.perceptual_hash) {
+                    Some(hash) => hash as u64,
+                    None => hash_image(self.fetch_image(&url).await?).await?,
+                };
+
+                (hash, known_image, Some(url))
+            }
+        };
+
+        let distance = Expr::cust_with_values(
+            r#"bit_count(("image"."perceptual_hash" # $1)::bit(64))"#,
+            [hash as i64],
+        );
+
+        let mut select = entity::image::Entity::find()
+            .filter(entity::image::Column::PerceptualHash.is_not_null())
+            .filter(Expr::expr(distance.clone()).lte(MAX_HASH_DISTANCE));
+
+        if let Some(query_url) = query_url {
+            select = select.filter(entity::image::Column::Url.ne(query_url));
+        }
+
+        let list = select
+            .order_by(distance, Order::Asc)
+            .offset(request.page.saturating_sub(1) as u64 * SIMILAR_IMAGES_PER_PAGE)
+            .limit(SIMILAR_IMAGES_PER_PAGE)
+            .all(&self.db)
+            .await
+            .map_err(|err| Status::from_error(err.into()))?;
+
+        let results = images_to_results(&self.db, list)
+            .await
+            .map_err(|err| Status::from_error(err.into()))?;
+
+        let query_image = match query_image {
+            Some(image) => images_to_results(&self.db, vec![image])
+                .await
+                .map_err(|err| Status::from_error(err.into()))?
+                .pop(),
+            None => None,
+        };
+
+        Ok(Response::new(SearchImageResponse {
+            results,
+            query_image,
+        }))
+    }
+
+    async fn search_video(
+        &self,
+        request: tonic::Request<SearchVideoRequest>,
+    ) -> std::result::Result<tonic::Response<SearchVideoResponse>, tonic::Status> {
         let request = request.into_inner();
         let query = request
             .query
@@ -125,9 +362,9 @@ impl proto::search::search_server::Search for SearchServise {
             .await
             .map_err(|err| Status::from_error(err.into()))?;
 
-        let result: SearchResults<Image> = self
+        let result: SearchResults<Media> = self
             .search_client
-            .index("image")
+            .index("video")
             .search()
             .with_query(&query.query)
             .with_page(query.page as usize)
@@ -135,8 +372,8 @@ impl proto::search::search_server::Search for SearchServise {
             .await
             .map_err(|err| Status::from_error(err.into()))?;
 
-        let list = join_all(result.hits.iter().map(|web| {
-            entity::image::Entity::find_by_id(web.result.id as i32)
+        let list = join_all(result.hits.iter().map(|video| {
+            entity::video::Entity::find_by_id(video.result.id as i32)
                 .find_also_related(websites::Entity)
                 .one(&self.db)
         }))
@@ -148,32 +385,268 @@ impl proto::search::search_server::Search for SearchServise {
 
         let results = list
             .into_iter()
-            .map(|(image_model, website_model)| {
-                let website_model = website_model.unwrap();
-                SearchImageResult {
-                    url: image_model.url,
-                    alt_text: image_model.alt_text,
-                    size: image_model
-                        .width
-                        .zip(image_model.height)
-                        .map(|(width, height)| Size {
-                            width: width as u32,
-                            height: height as u32,
-                        }),
-                    source: Some(SearchWebResult {
-                        url: website_model.url.clone(),
-                        title: website_model.title.clone(),
-                        description: website_model.description.clone(),
-                        icon_url: website_model.icon_url.clone(),
-                        inner_text_match: None,
-                        site_name: website_model.site_name.clone(),
-                        site_description: website_model.site_description.clone(),
+            .map(|(video_model, website_model)| SearchVideoResult {
+                url: video_model.url,
+                title: video_model.title,
+                mime_type: video_model.mime_type,
+                size: video_model
+                    .width
+                    .zip(video_model.height)
+                    .map(|(width, height)| Size {
+                        width: width as u32,
+                        height: height as u32,
                     }),
-                }
+                length_millis: video_model.length_millis.map(|length| length as u32),
+                poster_url: video_model.poster_url,
+                source: website_model.map(website_to_result),
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Response::new(SearchVideoResponse { results }))
+    }
+
+    async fn search_audio(
+        &self,
+        request: tonic::Request<SearchAudioRequest>,
+    ) -> std::result::Result<tonic::Response<SearchAudioResponse>, tonic::Status> {
+        let request = request.into_inner();
+        let query = request
+            .query
+            .ok_or(Status::invalid_argument("must have query"))?;
+
+        save_search_to_history(&self.db, &query.query)
+            .await
+            .map_err(|err| Status::from_error(err.into()))?;
+
+        let result: SearchResults<Media> = self
+            .search_client
+            .index("audio")
+            .search()
+            .with_query(&query.query)
+            .with_page(query.page as usize)
+            .execute()
+            .await
+            .map_err(|err| Status::from_error(err.into()))?;
+
+        let list = join_all(result.hits.iter().map(|audio| {
+            entity::audio::Entity::find_by_id(audio.result.id as i32)
+                .find_also_related(websites::Entity)
+                .one(&self.db)
+        }))
+        .await
+        .into_iter()
+        .collect::<Result<Option<Vec<_>>, _>>()
+        .map_err(|err| Status::from_error(err.into()))?
+        .ok_or(Status::internal("desync between postgres and meiliseach"))?;
+
+        let results = list
+            .into_iter()
+            .map(|(audio_model, website_model)| SearchAudioResult {
+                url: audio_model.url,
+                title: audio_model.title,
+                mime_type: audio_model.mime_type,
+                length_millis: audio_model.length_millis.map(|length| length as u32),
+                source: website_model.map(website_to_result),
             })
             .collect::<Vec<_>>();
 
-        Ok(Response::new(SearchImageResponse { results }))
+        Ok(Response::new(SearchAudioResponse { results }))
+    }
+
+    async fn get_image(
+        &self,
+        request: tonic::Request<GetImageRequest>,
+    ) -> std::result::Result<tonic::Response<GetImageResponse>, tonic::Status> {
+        let request = request.into_inner();
+
+        let image = entity::image::Entity::find_by_id(request.id)
+            .one(&self.db)
+            .await
+            .map_err(|err| Status::from_error(err.into()))?
+            .ok_or(Status::not_found("image not found"))?;
+
+        let thumbnail = if request.thumbnail && image.has_thumbnail {
+            Some(
+                self.thumbnails
+                    .get(image.id)
+                    .await
+                    .map_err(|err| Status::from_error(err.into()))?,
+            )
+        } else {
+            None
+        };
+
+        Ok(Response::new(GetImageResponse {
+            full_url: largest_variant(&image.variants),
+            url: image.url,
+            thumbnail: thumbnail.map(Vec::from),
+        }))
+    }
+}
+
+/// Converts canonical images to results, each showing the latest page it was found on.
+async fn images_to_results(
+    db: &DatabaseConnection,
+    images: Vec<entity::image::Model>,
+) -> anyhow::Result<Vec<SearchImageResult>> {
+    let mut sources =
+        image_sources(db, &images.iter().map(|image| image.id).collect::<Vec<_>>()).await?;
+
+    Ok(images
+        .into_iter()
+        .map(|image_model| {
+            let sources = sources.remove(&image_model.id);
+
+            SearchImageResult {
+                full_url: largest_variant(&image_model.variants),
+                url: image_model.url,
+                alt_text: image_model.alt_text,
+                size: image_model
+                    .width
+                    .zip(image_model.height)
+                    .map(|(width, height)| Size {
+                        width: width as u32,
+                        height: height as u32,
+                    }),
+                source: sources
+                    .as_ref()
+                    .and_then(|sources| sources.website.clone())
+                    .map(website_to_result),
+                id: image_model.id,
+                has_thumbnail: image_model.has_thumbnail,
+                page_count: sources
+                    .map(|sources| sources.page_count)
+                    .unwrap_or_default(),
+            }
+        })
+        .collect())
+}
+
+/// Decodes an uploaded or fetched image and returns its difference hash.
+async fn hash_image(data: Bytes) -> Result<u64, Status> {
+    spawn_blocking(move || {
+        let mut limits = image::io::Limits::default();
+        limits.max_image_width = Some(MAX_SIMILAR_IMAGE_DIMENSION);
+        limits.max_image_height = Some(MAX_SIMILAR_IMAGE_DIMENSION);
+        limits.max_alloc = Some(MAX_SIMILAR_IMAGE_ALLOC_BYTES);
+
+        let mut reader = image::io::Reader::new(Cursor::new(&data)).with_guessed_format()?;
+        reader.limits(limits);
+
+        anyhow::Ok(image_hash::dhash(&reader.decode()?))
+    })
+    .await
+    .map_err(|err| Status::from_error(err.into()))?
+    .map_err(|err| {
+        tracing::debug!("decoding image for similarity search failed: {}", err);
+        Status::invalid_argument("could not decode image")
+    })
+}
+
+/// Removes a `filetype:` operator from the query, returning the remaining text and the
+/// document type it asked for. Unknown file types are left in the query as plain text.
+fn split_filetype(query: &str) -> (String, Option<DocumentType>) {
+    let mut document_type = None;
+
+    let text = query
+        .split_whitespace()
+        .filter(|word| {
+            let filetype = word.strip_prefix("filetype:").and_then(|filetype| {
+                match filetype.to_ascii_lowercase().as_str() {
+                    "html" | "htm" => Some(DocumentType::Html),
+                    "pdf" => Some(DocumentType::Pdf),
+                    "txt" | "text" => Some(DocumentType::PlainText),
+                    "md" | "markdown" => Some(DocumentType::Markdown),
+                    "xml" => Some(DocumentType::Xml),
+                    _ => None,
+                }
+            });
+
+            match filetype {
+                Some(filetype) => {
+                    document_type = Some(filetype);
+                    false
+                }
+                None => true,
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    (text, document_type)
+}
+
+/// Points each result's preview image at our crawled copy of it. Images that were never
+/// crawled get no preview, since hotlinking them would show the user's address to the
+/// image's host.
+async fn attach_image_ids(
+    db: &DatabaseConnection,
+    results: &mut [SearchWebResult],
+) -> Result<(), sea_orm::DbErr> {
+    let normalized_urls = results
+        .iter()
+        .map(|result| {
+            result
+                .image_url
+                .as_deref()
+                .and_then(|url| normalize_image_url(url).ok())
+        })
+        .collect::<Vec<_>>();
+
+    let wanted = normalized_urls
+        .iter()
+        .flatten()
+        .cloned()
+        .collect::<Vec<_>>();
+    if wanted.is_empty() {
+        return Ok(());
+    }
+
+    let ids = entity::image::Entity::find()
+        .filter(entity::image::Column::NormalizedUrl.is_in(wanted))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|image| (image.normalized_url, image.id))
+        .collect::<HashMap<_, _>>();
+
+    for (result, url) in results.iter_mut().zip(normalized_urls) {
+        result.image_id = url.and_then(|url| ids.get(&url).copied());
+    }
+
+    Ok(())
+}
+
+/// Builds a result from a stored website, falling back to OpenGraph and Twitter card
+/// metadata for anything the page itself did not provide.
+fn website_to_result(model: websites::Model) -> SearchWebResult {
+    SearchWebResult {
+        url: model.url,
+        title: model
+            .title
+            .filter(|title| !title.trim().is_empty())
+            .or(model.og_title)
+            .or(model.twitter_title),
+        description: model
+            .description
+            .or(model.og_description)
+            .or(model.twitter_description),
+        icon_url: model.icon_url,
+        inner_text_match: None,
+        site_name: model.site_name.or(model.og_site_name),
+        site_description: model.site_description,
+        image_url: model.og_image.or(model.twitter_image),
+        rich_result: model.json_ld.as_ref().and_then(rich_result::from_json_ld),
+        document_type: match model.document_type {
+            DocumentType::Html => proto::search::DocumentType::Html,
+            DocumentType::Pdf => proto::search::DocumentType::Pdf,
+            DocumentType::PlainText => proto::search::DocumentType::PlainText,
+            DocumentType::Markdown => proto::search::DocumentType::Markdown,
+            DocumentType::Xml => proto::search::DocumentType::Xml,
+        } as i32,
+        page_count: model.page_count.map(|count| count as u32),
+        author: model.author,
+        image_id: None,
     }
 }
 
@@ -217,6 +690,12 @@ struct Image {
     url: String,
 }
 
+#[derive(Serialize, Deserialize)]
+struct Media {
+    id: i64,
+    url: String,
+}
+
 #[derive(Serialize, Deserialize)]
 struct SearchHistory {
     id: i64,
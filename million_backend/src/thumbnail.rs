@@ -0,0 +1,60 @@
+use std::sync::Arc;
+
+use object_store::{aws::AmazonS3Builder, local::LocalFileSystem, path::Path, ObjectStore};
+use proto::prost::bytes::Bytes;
+use url::Url;
+
+/// Where image thumbnails are kept, either a local directory (`file://`) or an
+/// S3-compatible bucket (`s3://bucket/prefix`, configured through the usual `AWS_*`
+/// environment variables).
+#[derive(Debug, Clone)]
+pub struct ThumbnailStore {
+    store: Arc<dyn ObjectStore>,
+    prefix: Path,
+}
+
+impl ThumbnailStore {
+    pub fn new(url: &Url) -> anyhow::Result<Self> {
+        match url.scheme() {
+            "file" => {
+                let dir = url
+                    .to_file_path()
+                    .map_err(|_| anyhow::anyhow!("invalid thumbnail directory: {}", url))?;
+                std::fs::create_dir_all(&dir)?;
+
+                Ok(Self {
+                    store: Arc::new(LocalFileSystem::new_with_prefix(dir)?),
+                    prefix: Path::default(),
+                })
+            }
+            "s3" => Ok(Self {
+                store: Arc::new(AmazonS3Builder::from_env().with_url(url.as_str()).build()?),
+                prefix: Path::from(url.path()),
+            }),
+            scheme => Err(anyhow::anyhow!(
+                "unsupported thumbnail store scheme: {}",
+                scheme
+            )),
+        }
+    }
+
+    pub async fn put(&self, image_id: i32, thumbnail: Vec<u8>) -> anyhow::Result<()> {
+        self.store
+            .put(&self.path(image_id), thumbnail.into())
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get(&self, image_id: i32) -> anyhow::Result<Bytes> {
+        Ok(self.store.get(&self.path(image_id)).await?.bytes().await?)
+    }
+
+    pub async fn delete(&self, image_id: i32) -> anyhow::Result<()> {
+        self.store.delete(&self.path(image_id)).await?;
+        Ok(())
+    }
+
+    fn path(&self, image_id: i32) -> Path {
+        self.prefix.child(format!("{}.webp", image_id))
+    }
+}
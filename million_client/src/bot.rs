@@ -0,0 +1,46 @@
+use std::sync::Arc;
+
+use axum::extract::State;
+use maud::{html, Markup};
+
+use crate::{utils::basic_page, AppState};
+
+/// Explains the crawler to site operators. Its `User-Agent` links here.
+pub async fn bot_page(State(state): State<Arc<AppState>>) -> Markup {
+    let bot_name = &state.bot_name;
+
+    basic_page(html! {
+        main class="max-w-2xl mx-auto px-4 py-10 flex flex-col gap-4" {
+            h1 class="font-bold tracking-tight text-4xl" { (bot_name) }
+            p {
+                (bot_name) " is the crawler behind "
+                a class="underline" href="/" { "Million Search" }
+                ". It fetches public pages, images, videos, audio, PDFs and feeds so they can \
+                 be found through the search engine."
+            }
+
+            h2 class="font-bold text-2xl" { "Identifying it" }
+            p { "Every request carries a " code { "User-Agent" } " like:" }
+            pre class="bg-neutral-100 dark:bg-zinc-900 rounded p-2 overflow-x-auto" {
+                "Mozilla/5.0 (compatible; " (bot_name) "/<version>; +<this page>)"
+            }
+            p {
+                "Pages are requested with conditional headers once they have been crawled, \
+                 so unchanged pages are not downloaded again. The crawler never follows \
+                 links into private or internal networks."
+            }
+
+            h2 class="font-bold text-2xl" { "Contact" }
+            @if let Some(contact) = &state.bot_contact {
+                p {
+                    "If the crawler causes problems for your site, or you want it to stay \
+                     away, write to "
+                    a class="underline" href=(format!("mailto:{}", contact)) { (contact) }
+                    "."
+                }
+            } @else {
+                p { "If the crawler causes problems for your site, contact the operator of this instance." }
+            }
+        }
+    })
+}
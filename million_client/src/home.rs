@@ -2,31 +2,21 @@ use axum::http::StatusCode;
 use maud::{html, Markup};
 
 use crate::{
-    utils::{basic_page, search_bar},
+    utils::{basic_page, search_bar, similar_image_form},
     SearchType,
 };
 
 pub async fn home_search_page(search_type: SearchType) -> Result<Markup, StatusCode> {
-    let sub_text = match search_type {
-        SearchType::Html => None,
-        SearchType::Image => Some("Images"),
-    };
+    let sub_text = (search_type != SearchType::Html).then(|| search_type.name());
 
-    let search_url = match search_type {
-        SearchType::Html => "/search",
-        SearchType::Image => "/image/search",
-    };
+    let search_url = search_type.search_url();
 
     Ok(basic_page(html! {
         div class="h-lvh flex flex-col items-center justify-center dark:bg-zinc-800 dark:text-zinc-50" {
             div class="ml-2 flex flex-row gap-4 self-start" {
-                @if search_type != SearchType::Html {
-                    a href="/"  {
-                        "Web"
-                    }
-                } @else {
-                    a href="/image"  {
-                        "Images"
+                @for other in SearchType::ALL.iter().filter(|other| **other != search_type) {
+                    a href=(other.home_url())  {
+                        (other.name())
                     }
                 }
 
@@ -45,12 +35,16 @@ pub async fn home_search_page(search_type: SearchType) -> Result<Markup, StatusC
                 form action=(search_url) autocomplete="off" class="flex flex-row items-center" {
                     (search_bar(""))
                 }
+                @if search_type == SearchType::Image {
+                    (similar_image_form())
+                }
             }
 
             div class="flex-[2]" {}
 
             footer class="bg-neutral-100 grid dark:bg-zinc-900 grid-cols-3 w-full px-4 py-2 gap-2" {
                 a href="https://dryicons.com/icon/search-2621" {"Icon by Dryicons"}
+                a href="/bot" {"About our crawler"}
             }
         }
 
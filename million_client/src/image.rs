@@ -0,0 +1,122 @@
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+};
+use million_common::net::read_capped;
+use proto::search::GetImageRequest;
+
+use crate::AppState;
+
+/// Largest original image the proxy will pass through.
+const MAX_PROXIED_IMAGE_BYTES: usize = 20 * 1024 * 1024;
+
+/// Image hosts are crawled, not trusted, so the proxy gives up on slow or redirect-happy
+/// ones instead of holding a connection open.
+pub const PROXY_TIMEOUT: Duration = Duration::from_secs(15);
+pub const PROXY_MAX_REDIRECTS: usize = 5;
+
+/// Thumbnails never change for a given image id.
+const THUMBNAIL_CACHE_CONTROL: &str = "public, max-age=604800, immutable";
+
+const PROXY_CACHE_CONTROL: &str = "public, max-age=86400";
+
+/// Serves the stored WebP thumbnail for an image, falling back to the proxied original
+/// for images the crawler could not decode.
+pub async fn thumbnail(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> Result<Response, (StatusCode, String)> {
+    let image = state
+        .client
+        .lock()
+        .await
+        .get_image(GetImageRequest {
+            id,
+            thumbnail: true,
+        })
+        .await
+        .map_err(|err| (StatusCode::NOT_FOUND, err.message().to_owned()))?
+        .into_inner();
+
+    let Some(thumbnail) = image.thumbnail else {
+        return Ok(Redirect::to(&format!("/image/proxy/{}", id)).into_response());
+    };
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "image/webp"),
+            (header::CACHE_CONTROL, THUMBNAIL_CACHE_CONTROL),
+        ],
+        thumbnail,
+    )
+        .into_response())
+}
+
+/// Fetches a full-size image, the largest listed resolution when there are several, on the
+/// user's behalf so the original host never sees their address, cookies or referrer.
+pub async fn image_proxy(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> Result<Response, (StatusCode, String)> {
+    let image = state
+        .client
+        .lock()
+        .await
+        .get_image(GetImageRequest {
+            id,
+            thumbnail: false,
+        })
+        .await
+        .map_err(|err| (StatusCode::NOT_FOUND, err.message().to_owned()))?
+        .into_inner();
+
+    let upstream_error = |err: &dyn std::fmt::Display| {
+        tracing::debug!("proxying image {} failed: {}", id, err);
+        (
+            StatusCode::BAD_GATEWAY,
+            String::from("could not fetch upstream image"),
+        )
+    };
+
+    let res = state
+        .http_client
+        .get(image.full_url.as_deref().unwrap_or(&image.url))
+        .send()
+        .await
+        .and_then(|res| res.error_for_status())
+        .map_err(|err| upstream_error(&err))?;
+
+    let content_type = res
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .map(|content_type| content_type.to_ascii_lowercase())
+        .filter(|content_type| content_type.starts_with("image/"))
+        .ok_or((
+            StatusCode::BAD_GATEWAY,
+            String::from("upstream did not return an image"),
+        ))?;
+
+    let bytes = read_capped(res, MAX_PROXIED_IMAGE_BYTES)
+        .await
+        .map_err(|err| upstream_error(&err))?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.as_str()),
+            (header::CACHE_CONTROL, PROXY_CACHE_CONTROL),
+            (header::X_CONTENT_TYPE_OPTIONS, "nosniff"),
+            // SVGs are served from our origin, so keep them from running scripts.
+            (
+                header::CONTENT_SECURITY_POLICY,
+                "default-src 'none'; style-src 'unsafe-inline'",
+            ),
+        ],
+        Body::from(bytes),
+    )
+        .into_response())
+}
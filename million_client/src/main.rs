@@ -4,14 +4,18 @@ use std::{
 };
 
 use axum::{
-    extract::State,
-    http::StatusCode,
+    extract::{DefaultBodyLimit, Multipart, State},
+    http::{HeaderMap, StatusCode},
     routing::{get, post},
     Form, Router,
 };
+use bot::bot_page;
 use clap::Parser;
 use home::home_search_page;
+use image::{image_proxy, thumbnail, PROXY_MAX_REDIRECTS, PROXY_TIMEOUT};
 use maud::Markup;
+use million_common::net::AddressFilter;
+use proto::search::search_image_by_similarity_request;
 use proto::{
     search::search_client::SearchClient,
     tonic::{
@@ -19,16 +23,24 @@ use proto::{
         transport::{Channel, Uri},
     },
 };
-use search::{image_view, search_page, search_page_results, ViewData};
+use search::{
+    image_view, search_page, search_page_results, search_page_results_html, similar_image_page,
+    SimilarImageQuery, ViewData,
+};
 use serde::{Deserialize, Serialize};
 use tokio::sync::Mutex;
 use tower_http::services::ServeDir;
 use tracing_subscriber::EnvFilter;
-use utils::search_suggestions;
+use utils::{preferred_language, search_suggestions};
+mod bot;
 mod home;
+mod image;
 mod search;
 mod utils;
 
+/// Largest image accepted by the reverse image search upload form.
+const MAX_UPLOAD_BYTES: usize = 10 * 1024 * 1024;
+
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 struct Args {
@@ -40,10 +52,23 @@ struct Args {
 
     #[arg(short, long, env, default_value_t = 3000)]
     port: u16,
+
+    /// Crawler name shown on the `/bot` page; should match the crawler's `--bot-name`.
+    #[arg(long, env, default_value_t = String::from("MillionSearchBot"))]
+    bot_name: String,
+
+    /// Address site operators can write to about the crawler.
+    #[arg(long, env)]
+    bot_contact: Option<String>,
 }
 
 struct AppState {
     client: Mutex<SearchClient<Channel>>,
+    /// Used to proxy original images; sends no cookies or referrer and only reaches
+    /// public addresses.
+    http_client: reqwest::Client,
+    bot_name: String,
+    bot_contact: Option<String>,
 }
 
 #[tokio::main]
@@ -61,6 +86,9 @@ async fn main() -> anyhow::Result<()> {
 
     let state = Arc::new(AppState {
         client: Mutex::new(client),
+        http_client: AddressFilter::default().client(PROXY_TIMEOUT, PROXY_MAX_REDIRECTS)?,
+        bot_name: args.bot_name,
+        bot_contact: args.bot_contact,
     });
 
     let app = Router::new()
@@ -72,7 +100,26 @@ async fn main() -> anyhow::Result<()> {
             get(search_image).post(search_image_results),
         )
         .route("/image/search/view", post(image_view))
+        .route(
+            "/image/similar",
+            get(similar_image_url)
+                .post(similar_image_upload)
+                .layer(DefaultBodyLimit::max(MAX_UPLOAD_BYTES)),
+        )
+        .route("/image/proxy/:id", get(image_proxy))
+        .route("/thumb/:id", get(thumbnail))
+        .route("/video", get(home_search_video))
+        .route(
+            "/video/search",
+            get(search_video).post(search_video_results),
+        )
+        .route("/audio", get(home_search_audio))
+        .route(
+            "/audio/search",
+            get(search_audio).post(search_audio_results),
+        )
         .route("/search-suggestions", post(search_suggestions))
+        .route("/bot", get(bot_page))
         .nest_service("/public", ServeDir::new("public"))
         .with_state(state)
         .layer(tower_http::compression::CompressionLayer::new());
@@ -89,6 +136,44 @@ async fn main() -> anyhow::Result<()> {
 enum SearchType {
     Html,
     Image,
+    Video,
+    Audio,
+}
+
+impl SearchType {
+    const ALL: [SearchType; 4] = [
+        SearchType::Html,
+        SearchType::Image,
+        SearchType::Video,
+        SearchType::Audio,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            SearchType::Html => "Web",
+            SearchType::Image => "Images",
+            SearchType::Video => "Videos",
+            SearchType::Audio => "Audio",
+        }
+    }
+
+    fn home_url(&self) -> &'static str {
+        match self {
+            SearchType::Html => "/",
+            SearchType::Image => "/image",
+            SearchType::Video => "/video",
+            SearchType::Audio => "/audio",
+        }
+    }
+
+    fn search_url(&self) -> &'static str {
+        match self {
+            SearchType::Html => "/search",
+            SearchType::Image => "/image/search",
+            SearchType::Video => "/video/search",
+            SearchType::Audio => "/audio/search",
+        }
+    }
 }
 
 async fn home_search_html() -> Result<Markup, StatusCode> {
@@ -97,12 +182,20 @@ async fn home_search_html() -> Result<Markup, StatusCode> {
 async fn home_search_image() -> Result<Markup, StatusCode> {
     home_search_page(SearchType::Image).await
 }
+async fn home_search_video() -> Result<Markup, StatusCode> {
+    home_search_page(SearchType::Video).await
+}
+async fn home_search_audio() -> Result<Markup, StatusCode> {
+    home_search_page(SearchType::Audio).await
+}
 
 #[derive(Deserialize, Serialize)]
 struct SearchQuery {
     query: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     page: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    language: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none", flatten)]
     image_params: Option<ImageParams>,
 }
@@ -111,10 +204,26 @@ struct SearchQuery {
 struct ImageParams {
     #[serde(skip_serializing_if = "Option::is_none", flatten)]
     size_range: Option<SizeRange>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    color: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    brightness: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none", flatten)]
     selected: Option<ViewData>,
 }
 
+impl ImageParams {
+    /// The same filters without the image selected in the side view.
+    fn filters(&self) -> ImageParams {
+        ImageParams {
+            size_range: self.size_range.clone(),
+            color: self.color.clone(),
+            brightness: self.brightness.clone(),
+            selected: None,
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct SizeRange {
     pub min_width: u32,
@@ -136,8 +245,22 @@ async fn search_image(
     search_page(SearchType::Image, query, state).await
 }
 
+async fn search_video(
+    State(state): State<Arc<AppState>>,
+    Form(query): Form<SearchQuery>,
+) -> Result<Markup, StatusCode> {
+    search_page(SearchType::Video, query, state).await
+}
+async fn search_audio(
+    State(state): State<Arc<AppState>>,
+    Form(query): Form<SearchQuery>,
+) -> Result<Markup, StatusCode> {
+    search_page(SearchType::Audio, query, state).await
+}
+
 async fn search_html_results(
     State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
     Form(query): Form<SearchQuery>,
 ) -> Result<Markup, (StatusCode, String)> {
     if query.image_params.is_some() {
@@ -147,7 +270,14 @@ async fn search_html_results(
         ));
     }
 
-    search_page_results(SearchType::Html, query, state).await
+    search_page_results_html(
+        query.query,
+        query.page.unwrap_or(1),
+        query.language,
+        preferred_language(&headers),
+        state,
+    )
+    .await
 }
 async fn search_image_results(
     State(state): State<Arc<AppState>>,
@@ -155,3 +285,56 @@ async fn search_image_results(
 ) -> Result<Markup, (StatusCode, String)> {
     search_page_results(SearchType::Image, query, state).await
 }
+async fn search_video_results(
+    State(state): State<Arc<AppState>>,
+    Form(query): Form<SearchQuery>,
+) -> Result<Markup, (StatusCode, String)> {
+    search_page_results(SearchType::Video, query, state).await
+}
+async fn search_audio_results(
+    State(state): State<Arc<AppState>>,
+    Form(query): Form<SearchQuery>,
+) -> Result<Markup, (StatusCode, String)> {
+    search_page_results(SearchType::Audio, query, state).await
+}
+
+async fn similar_image_url(
+    State(state): State<Arc<AppState>>,
+    Form(query): Form<SimilarImageQuery>,
+) -> Result<Markup, (StatusCode, String)> {
+    similar_image_page(
+        search_image_by_similarity_request::Image::Url(query.url),
+        query.page.unwrap_or(1),
+        state,
+    )
+    .await
+}
+async fn similar_image_upload(
+    State(state): State<Arc<AppState>>,
+    mut multipart: Multipart,
+) -> Result<Markup, (StatusCode, String)> {
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|err| (StatusCode::BAD_REQUEST, err.to_string()))?
+    {
+        if field.name() == Some("image") {
+            let data = field
+                .bytes()
+                .await
+                .map_err(|err| (StatusCode::BAD_REQUEST, err.to_string()))?;
+
+            return similar_image_page(
+                search_image_by_similarity_request::Image::Data(data.to_vec()),
+                1,
+                state,
+            )
+            .await;
+        }
+    }
+
+    Err((
+        StatusCode::BAD_REQUEST,
+        String::from("missing image upload"),
+    ))
+}
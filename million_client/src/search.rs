@@ -7,12 +7,20 @@ use axum::{
 };
 use maud::{html, Markup};
 
-use proto::search::{SearchImageRequest, SearchImageResult, SearchWebRequest, SearchWebResult};
+use proto::search::{
+    search_image_by_similarity_request, search_web_result::RichResult, Brightness, DocumentType,
+    Rating, SearchAudioRequest, SearchAudioResult, SearchImageBySimilarityRequest,
+    SearchImageRequest, SearchImageResult, SearchVideoRequest, SearchVideoResult, SearchWebRequest,
+    SearchWebResult,
+};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    utils::{basic_page, search_bar},
-    AppState, SearchQuery, SearchType,
+    utils::{
+        basic_page, image_filter_select, language_select, search_bar, similar_image_form,
+        ANY_LANGUAGE,
+    },
+    AppState, ImageParams, SearchQuery, SearchType,
 };
 
 pub async fn search_page(
@@ -23,15 +31,13 @@ pub async fn search_page(
     let url_params =
         serde_url_params::to_string(&query).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    let search_url = match search_type {
-        SearchType::Html => "/search",
-        SearchType::Image => "/image/search",
-    };
+    let search_url = search_type.search_url();
 
     let search_params = serde_json::to_string(&SearchQuery {
         query: query.query.clone(),
         page: None,
-        image_params: query.image_params,
+        language: query.language.clone(),
+        image_params: query.image_params.as_ref().map(ImageParams::filters),
     })
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
@@ -39,43 +45,39 @@ pub async fn search_page(
         div class="h-lvh flex flex-col items-start dark:bg-zinc-800 dark:text-zinc-50 overflow-hidden" {//min-h-lvh
             header class="flex flex-col pt-6 pb-2 border-b-2 border-neutral-200 dark:border-zinc-700 w-full items-center " {
                 div class="flex flex-row w-full items-center" {
-                    @match &search_type {
-                        SearchType::Html => {
-                            a href="/" class="flex flex-col items-center"  {
-                                h1 class="font-bold tracking-tight text-3xl ml-6 mr-12 text-center" {
-                                    "Million Search"
-                                }
-                            }
+                    a href=(search_type.home_url()) class="flex flex-col items-center"  {
+                        h1 class="font-bold tracking-tight text-3xl ml-6 mr-12 text-center" {
+                            "Million Search"
                         }
-                        SearchType::Image => {
-                            a href="/image" class="flex flex-col items-center"  {
-                                h1 class="font-bold tracking-tight text-3xl ml-6 mr-12 text-center" {
-                                    "Million Search"
-                                }
-                                span class="trackinng-tight text-lg text-center" {
-                                    "Images"
-                                }
+                        @if search_type != SearchType::Html {
+                            span class="trackinng-tight text-lg text-center" {
+                                (search_type.name())
                             }
                         }
                     }
 
                     form action=(search_url) autocomplete="off" class="flex flex-row items-center" {
                         (search_bar(&query.query))
+                        @if search_type == SearchType::Html {
+                            (language_select(query.language.as_deref()))
+                        }
+                        @if search_type == SearchType::Image {
+                            (image_filter_select(
+                                query.image_params.as_ref().and_then(|params| params.color.as_deref()),
+                                query.image_params.as_ref().and_then(|params| params.brightness.as_deref()),
+                            ))
+                        }
+                    }
+                    @if search_type == SearchType::Image {
+                        (similar_image_form())
                     }
                 }
                 div class="flex flex-row gap-4 self-start pl-4 pt-2" {
 
 
-                    @match &search_type {
-                        SearchType::Html => {
-                            a href=("/image/search?".to_owned() + &url_params)  {
-                                "Images"
-                            }
-                        }
-                        SearchType::Image => {
-                            a href=("/search?".to_owned() + &url_params)  {
-                                "Web"
-                            }
+                    @for other in SearchType::ALL.iter().filter(|other| **other != search_type) {
+                        a href=(other.search_url().to_owned() + "?" + &url_params)  {
+                            (other.name())
                         }
                     }
                 }
@@ -97,6 +99,16 @@ pub async fn search_page(
                             div id="image-view" {}
                         }
                     },
+                    SearchType::Video => {
+                        div class="flex flex-row flex-wrap content-start h-full overflow-y-scroll" {
+                            div hx-post=(search_url) hx-trigger="intersect once" hx-swap="outerHTML" hx-vals=(search_params) {}
+                        }
+                    }
+                    SearchType::Audio => {
+                        div class="flex flex-col h-full overflow-y-scroll" {
+                            div hx-post=(search_url) hx-trigger="intersect once" hx-swap="outerHTML" hx-vals=(search_params) {}
+                        }
+                    }
                 }
             }
 
@@ -116,17 +128,40 @@ pub async fn search_page_results(
     // tokio::time::sleep(Duration::from_secs(2)).await; // use for loading spinner testing
     match search_type {
         SearchType::Html => {
-            search_page_results_html(query.query, query.page.unwrap_or(1), state).await
+            search_page_results_html(
+                query.query,
+                query.page.unwrap_or(1),
+                query.language,
+                None,
+                state,
+            )
+            .await
         }
         SearchType::Image => {
-            search_page_results_image(query.query, query.page.unwrap_or(1), state).await
+            search_page_results_image(
+                query.query,
+                query.page.unwrap_or(1),
+                query.image_params,
+                state,
+            )
+            .await
+        }
+        SearchType::Video => {
+            search_page_results_video(query.query, query.page.unwrap_or(1), state).await
+        }
+        SearchType::Audio => {
+            search_page_results_audio(query.query, query.page.unwrap_or(1), state).await
         }
     }
 }
 
-async fn search_page_results_html(
+/// Web results, restricted to `language` when the user picked one, and otherwise leaning
+/// towards `preferred_language` from their browser.
+pub async fn search_page_results_html(
     query: String,
     page: u32,
+    language: Option<String>,
+    preferred_language: Option<String>,
     state: Arc<AppState>,
 ) -> Result<Markup, (StatusCode, String)> {
     let results = state
@@ -138,6 +173,8 @@ async fn search_page_results_html(
                 query: query.clone(),
                 page,
             }),
+            language: language.clone().filter(|language| language != ANY_LANGUAGE),
+            preferred_language: preferred_language.filter(|_| language.is_none()),
         })
         .await
         .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
@@ -147,6 +184,7 @@ async fn search_page_results_html(
     let search_params = serde_json::to_string(&SearchQuery {
         query: query.clone(),
         page: Some(page + 1),
+        language,
         image_params: None,
     })
     .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
@@ -184,24 +222,174 @@ fn render_html_result(result: &SearchWebResult) -> Markup {
                         }
                     }
                 }
-                h2 class="text-lg font-bold truncate" {
-                    (result.title.as_deref().unwrap_or(&result.url))
+                div class="flex flex-row items-center gap-2 min-w-0" {
+                    @if let Some(badge) = document_type_badge(result.document_type()) {
+                        span class="px-1.5 rounded text-xs font-bold bg-red-700 text-white" {
+                            (badge)
+                        }
+                    }
+                    h2 class="text-lg font-bold truncate" {
+                        (result.title.as_deref().unwrap_or(&result.url))
+                    }
+                }
+            }
+            @if result.page_count.is_some() || (result.document_type() != DocumentType::Html && result.author.is_some()) {
+                div class="flex flex-row gap-2 text-sm text-neutral-500 dark:text-zinc-400" {
+                    @if let Some(author) = &result.author {
+                        span { (author) }
+                    }
+                    @if let Some(page_count) = result.page_count {
+                        span { (page_count) " pages" }
+                    }
                 }
             }
-            @if result.inner_text_match.is_some() || result.description.is_some() {
-                p class="w-full sm:w-1/2" {
-                    (result.inner_text_match.as_deref().or(result.description.as_deref()).unwrap())
+            div class="flex flex-row gap-4 w-full sm:w-1/2" {
+                @if result.inner_text_match.is_some() || result.description.is_some() {
+                    p class="flex-1" {
+                        (result.inner_text_match.as_deref().or(result.description.as_deref()).unwrap())
+                    }
+                }
+                @if let Some(image_id) = result.image_id {
+                    img class="w-24 h-16 object-cover rounded-md bg-white" src=(format!("/thumb/{}", image_id)) alt="" loading="lazy" {}
                 }
             }
+            @if let Some(rich_result) = &result.rich_result {
+                (render_rich_result(rich_result))
+            }
         }
     }
 }
 
+fn render_rich_result(rich_result: &RichResult) -> Markup {
+    html! {
+        div class="mt-2 w-full sm:w-1/2 px-3 py-2 rounded-xl bg-neutral-100 dark:bg-zinc-700 text-sm" {
+            @match rich_result {
+                RichResult::Article(article) => {
+                    div class="flex flex-row flex-wrap gap-x-2" {
+                        @if !article.authors.is_empty() {
+                            span class="font-semibold" { (article.authors.join(", ")) }
+                        }
+                        @if let Some(publisher) = &article.publisher {
+                            span { (publisher) }
+                        }
+                        @if let Some(date) = &article.date_published {
+                            span class="text-neutral-500 dark:text-zinc-400" { (display_date(date)) }
+                        }
+                    }
+                }
+                RichResult::Recipe(recipe) => {
+                    div class="flex flex-row flex-wrap gap-x-4" {
+                        @if let Some(rating) = &recipe.rating {
+                            (render_rating(rating))
+                        }
+                        @if let Some(total_time) = &recipe.total_time {
+                            span { "Time: " (total_time) }
+                        }
+                        @if let Some(recipe_yield) = &recipe.recipe_yield {
+                            span { "Serves: " (recipe_yield) }
+                        }
+                        @if let Some(calories) = &recipe.calories {
+                            span { (calories) }
+                        }
+                    }
+                }
+                RichResult::Product(product) => {
+                    div class="flex flex-row flex-wrap gap-x-4 items-center" {
+                        @if let Some(price) = &product.price {
+                            span class="font-semibold text-base" {
+                                (price)
+                                @if let Some(currency) = &product.currency {
+                                    " " (currency)
+                                }
+                            }
+                        }
+                        @if let Some(availability) = &product.availability {
+                            span { (display_availability(availability)) }
+                        }
+                        @if let Some(brand) = &product.brand {
+                            span { (brand) }
+                        }
+                        @if let Some(rating) = &product.rating {
+                            (render_rating(rating))
+                        }
+                    }
+                }
+                RichResult::Event(event) => {
+                    div class="flex flex-col" {
+                        @if let Some(start_date) = &event.start_date {
+                            span class="font-semibold" {
+                                (display_date(start_date))
+                                @if let Some(end_date) = &event.end_date {
+                                    " – " (display_date(end_date))
+                                }
+                            }
+                        }
+                        @if let Some(location) = &event.location {
+                            span { (location) }
+                        }
+                    }
+                }
+                RichResult::Faq(faq) => {
+                    div class="flex flex-col divide-y divide-neutral-300 dark:divide-zinc-600" {
+                        @for question in &faq.questions {
+                            details class="py-1" {
+                                summary class="font-semibold cursor-pointer" { (question.question) }
+                                p { (question.answer) }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn render_rating(rating: &Rating) -> Markup {
+    html! {
+        span {
+            (format!("★ {:.1}", rating.value))
+            @if let Some(count) = rating.count {
+                span class="text-neutral-500 dark:text-zinc-400" { (format!(" ({})", count)) }
+            }
+        }
+    }
+}
+
+fn document_type_badge(document_type: DocumentType) -> Option<&'static str> {
+    match document_type {
+        DocumentType::Html => None,
+        DocumentType::Pdf => Some("PDF"),
+        DocumentType::PlainText => Some("TXT"),
+        DocumentType::Markdown => Some("MD"),
+        DocumentType::Xml => Some("XML"),
+    }
+}
+
+/// Drops the time of day from an ISO 8601 timestamp.
+fn display_date(date: &str) -> &str {
+    date.split('T').next().unwrap_or(date)
+}
+
+/// Splits a schema.org availability such as `InStock` into `In Stock`.
+fn display_availability(availability: &str) -> String {
+    let mut display = String::new();
+    for c in availability.chars() {
+        if c.is_uppercase() && !display.is_empty() {
+            display.push(' ');
+        }
+        display.push(c);
+    }
+    display
+}
+
 async fn search_page_results_image(
     query: String,
     page: u32,
+    image_params: Option<ImageParams>,
     state: Arc<AppState>,
 ) -> Result<Markup, (StatusCode, String)> {
+    let (color, brightness) = image_filters(image_params.as_ref());
+
     let results = state
         .client
         .lock()
@@ -212,6 +400,8 @@ async fn search_page_results_image(
                 page,
             }),
             size: None,
+            color,
+            brightness: brightness as i32,
         })
         .await
         .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
@@ -221,13 +411,14 @@ async fn search_page_results_image(
     let search_params = serde_json::to_string(&SearchQuery {
         query: query.clone(),
         page: Some(page + 1),
-        image_params: None,
+        language: None,
+        image_params: image_params.as_ref().map(ImageParams::filters),
     })
     .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
 
     Ok(html! {
         @for (i, result) in results.iter().enumerate() {
-            (render_image_result(result, page, i as u32).map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?)
+            (render_image_result(result, Some((page, i as u32))).map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?)
         }
         @if !results.is_empty() {
             div hx-post="/image/search" hx-trigger="intersect once" hx-swap="outerHTML" hx-vals=(search_params) {}
@@ -235,32 +426,341 @@ async fn search_page_results_image(
     })
 }
 
-fn render_image_result(result: &SearchImageResult, page: u32, i: u32) -> anyhow::Result<Markup> {
-    let view_data = serde_json::to_string(&ViewData {
-        page,
-        item: i as i32,
-    })?;
+/// Renders one image tile. With a `(page, item)` position, clicking the image opens it in
+/// the side view; without one the image links to a search for similar images.
+fn render_image_result(
+    result: &SearchImageResult,
+    position: Option<(u32, u32)>,
+) -> anyhow::Result<Markup> {
+    let view_data = position
+        .map(|(page, i)| {
+            serde_json::to_string(&ViewData {
+                page,
+                item: i as i32,
+            })
+        })
+        .transpose()?;
 
     Ok(html! {
         div class="flex flex-col m-4 w-fit max-w-48" {
-            img src=(result.url) class="min-h-12 max-h-36 object-contain rounded-md bg-white" alt=(result.alt_text())
-                hx-post="/image/search/view" hx-target="#image-view" hx-swap="outerHTML" hx-vals=(view_data) {}
+            @if let Some(view_data) = view_data {
+                img src=(thumbnail_url(result)) class="min-h-12 max-h-36 object-contain rounded-md bg-white" alt=(result.alt_text()) loading="lazy"
+                    hx-post="/image/search/view" hx-target="#image-view" hx-swap="outerHTML" hx-vals=(view_data) {}
+            } @else {
+                a href=(similar_image_url(&result.url)) {
+                    img src=(thumbnail_url(result)) class="min-h-12 max-h-36 object-contain rounded-md bg-white" alt=(result.alt_text()) loading="lazy" {}
+                }
+            }
 
-            a href=(result.source.as_ref().unwrap().url) class="min-w-0 flex flex-col" {
-                div class="flex flex-row items-center min-w-0" {
-                    img src=(result.source.as_ref().unwrap().icon_url.as_deref().unwrap_or("/public/gloabe.svg")) class="w-4 h-4 bg-white rounded-full mr-2 p-0.5" {}
+            @if let Some(source) = &result.source {
+                a href=(source.url) class="min-w-0 flex flex-col" {
+                    div class="flex flex-row items-center min-w-0" {
+                        img src=(source.icon_url.as_deref().unwrap_or("/public/gloabe.svg")) class="w-4 h-4 bg-white rounded-full mr-2 p-0.5" {}
+                        span class="text-ellipsis min-w-0 overflow-hidden whitespace-nowrap" {
+                            (display_site_name(source))
+                        }
+                    }
                     span class="text-ellipsis min-w-0 overflow-hidden whitespace-nowrap" {
-                        (display_site_name(result.source.as_ref().unwrap()))
+                        (result.alt_text())
                     }
                 }
+            } @else {
                 span class="text-ellipsis min-w-0 overflow-hidden whitespace-nowrap" {
                     (result.alt_text())
                 }
             }
+            @if result.page_count > 1 {
+                span class="text-sm text-neutral-500 dark:text-zinc-400" { (display_page_count(result.page_count)) }
+            }
         }
     })
 }
 
+fn display_page_count(page_count: u32) -> String {
+    match page_count {
+        1 => String::from("Found on 1 page"),
+        page_count => format!("Found on {} pages", page_count),
+    }
+}
+
+async fn search_page_results_video(
+    query: String,
+    page: u32,
+    state: Arc<AppState>,
+) -> Result<Markup, (StatusCode, String)> {
+    let results = state
+        .client
+        .lock()
+        .await
+        .search_video(SearchVideoRequest {
+            query: Some(proto::search::SearchQuery {
+                query: query.clone(),
+                page,
+            }),
+        })
+        .await
+        .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+        .into_inner()
+        .results;
+
+    let search_params = serde_json::to_string(&SearchQuery {
+        query: query.clone(),
+        page: Some(page + 1),
+        language: None,
+        image_params: None,
+    })
+    .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+
+    Ok(html! {
+        @for result in &results {
+            (render_video_result(result))
+        }
+        @if !results.is_empty() {
+            div hx-post="/video/search" hx-trigger="intersect once" hx-swap="outerHTML" hx-vals=(search_params) {}
+        }
+    })
+}
+
+fn render_video_result(result: &SearchVideoResult) -> Markup {
+    let title = result
+        .title
+        .as_deref()
+        .or(result
+            .source
+            .as_ref()
+            .and_then(|source| source.title.as_deref()))
+        .unwrap_or(result.url.as_str());
+
+    html! {
+        div class="flex flex-col m-4 w-64" {
+            video class="w-64 h-36 object-contain rounded-md bg-black" src=(result.url) poster=[result.poster_url.as_deref()] preload="none" controls {}
+
+            span class="font-semibold text-ellipsis min-w-0 overflow-hidden whitespace-nowrap" {
+                (title)
+            }
+            span class="text-sm" {
+                @if let Some(length_millis) = result.length_millis {
+                    (display_length(length_millis))
+                }
+                @if let Some(size) = &result.size {
+                    @if result.length_millis.is_some() {
+                        " · "
+                    }
+                    (size.width) "×" (size.height)
+                }
+            }
+            @if let Some(source) = &result.source {
+                (render_media_source(source))
+            }
+        }
+    }
+}
+
+async fn search_page_results_audio(
+    query: String,
+    page: u32,
+    state: Arc<AppState>,
+) -> Result<Markup, (StatusCode, String)> {
+    let results = state
+        .client
+        .lock()
+        .await
+        .search_audio(SearchAudioRequest {
+            query: Some(proto::search::SearchQuery {
+                query: query.clone(),
+                page,
+            }),
+        })
+        .await
+        .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+        .into_inner()
+        .results;
+
+    let search_params = serde_json::to_string(&SearchQuery {
+        query: query.clone(),
+        page: Some(page + 1),
+        language: None,
+        image_params: None,
+    })
+    .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+
+    Ok(html! {
+        @for result in &results {
+            (render_audio_result(result))
+        }
+        @if !results.is_empty() {
+            div hx-post="/audio/search" hx-trigger="intersect once" hx-swap="outerHTML" hx-vals=(search_params) {}
+        }
+    })
+}
+
+fn render_audio_result(result: &SearchAudioResult) -> Markup {
+    let title = result
+        .title
+        .as_deref()
+        .or(result
+            .source
+            .as_ref()
+            .and_then(|source| source.title.as_deref()))
+        .unwrap_or(result.url.as_str());
+
+    html! {
+        div class="my-4 flex flex-col max-w-2xl" {
+            div class="flex flex-row items-center gap-2 min-w-0" {
+                span class="font-semibold truncate" {
+                    (title)
+                }
+                @if let Some(length_millis) = result.length_millis {
+                    span class="text-sm" {
+                        (display_length(length_millis))
+                    }
+                }
+            }
+            audio class="w-full my-1" src=(result.url) preload="none" controls {}
+            @if let Some(source) = &result.source {
+                (render_media_source(source))
+            }
+        }
+    }
+}
+
+fn render_media_source(source: &SearchWebResult) -> Markup {
+    html! {
+        a href=(source.url) class="flex flex-row items-center min-w-0" {
+            img src=(source.icon_url.as_deref().unwrap_or("/public/gloabe.svg")) class="w-4 h-4 bg-white rounded-full mr-2 p-0.5" {}
+            span class="text-sm text-ellipsis min-w-0 overflow-hidden whitespace-nowrap" {
+                (display_site_name(source))
+            }
+        }
+    }
+}
+
+/// Formats a media length as `m:ss`, or `h:mm:ss` for anything an hour or longer.
+fn display_length(length_millis: u32) -> String {
+    let seconds = length_millis / 1000;
+
+    if seconds >= 3600 {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+/// Color and brightness filters from the image search params, ignoring "any" values.
+fn image_filters(image_params: Option<&ImageParams>) -> (Option<String>, Brightness) {
+    let color = image_params
+        .and_then(|params| params.color.clone())
+        .filter(|color| !color.is_empty());
+
+    let brightness = match image_params.and_then(|params| params.brightness.as_deref()) {
+        Some("dark") => Brightness::Dark,
+        Some("light") => Brightness::Light,
+        _ => Brightness::Any,
+    };
+
+    (color, brightness)
+}
+
+/// Crawled images are never hotlinked; the client serves their thumbnail or a proxied copy.
+fn thumbnail_url(result: &SearchImageResult) -> String {
+    if result.has_thumbnail {
+        format!("/thumb/{}", result.id)
+    } else {
+        format!("/image/proxy/{}", result.id)
+    }
+}
+
+fn similar_image_url(image_url: &str) -> String {
+    format!(
+        "/image/similar?{}",
+        serde_url_params::to_string(&SimilarImageQuery {
+            url: image_url.to_owned(),
+            page: None,
+        })
+        .unwrap_or_default()
+    )
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SimilarImageQuery {
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+}
+
+/// Results page for a reverse image search, either for an uploaded file or an image URL.
+pub async fn similar_image_page(
+    image: search_image_by_similarity_request::Image,
+    page: u32,
+    state: Arc<AppState>,
+) -> Result<Markup, (StatusCode, String)> {
+    let image_url = match &image {
+        search_image_by_similarity_request::Image::Url(url) => Some(url.clone()),
+        search_image_by_similarity_request::Image::Data(_) => None,
+    };
+
+    let response = state
+        .client
+        .lock()
+        .await
+        .search_image_by_similarity(SearchImageBySimilarityRequest {
+            image: Some(image),
+            page,
+        })
+        .await
+        .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+        .into_inner();
+    let results = response.results;
+
+    Ok(basic_page(html! {
+        div class="h-lvh flex flex-col items-start dark:bg-zinc-800 dark:text-zinc-50 overflow-hidden" {
+            header class="flex flex-col pt-6 pb-2 border-b-2 border-neutral-200 dark:border-zinc-700 w-full items-center" {
+                div class="flex flex-row w-full items-center" {
+                    a href="/image" class="flex flex-col items-center"  {
+                        h1 class="font-bold tracking-tight text-3xl ml-6 mr-12 text-center" {
+                            "Million Search"
+                        }
+                        span class="trackinng-tight text-lg text-center" {
+                            "Similar images"
+                        }
+                    }
+                    // Only indexed images are previewed, through our own thumbnail or proxy;
+                    // an arbitrary query URL is never loaded into the page.
+                    @if let Some(query_image) = &response.query_image {
+                        img src=(thumbnail_url(query_image)) class="h-16 object-contain rounded-md bg-white mr-4" alt="" {}
+                    }
+                    (similar_image_form())
+                }
+            }
+
+            div class="flex-1 px-6 pt-4 w-full overflow-y-scroll" {
+                @if results.is_empty() {
+                    span { "No similar images found." }
+                }
+                div class="flex flex-row flex-wrap" {
+                    @for result in &results {
+                        (render_image_result(result, None).map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?)
+                    }
+                }
+                @if let Some(image_url) = image_url.filter(|_| !results.is_empty()) {
+                    a class="block my-4" href=(format!("/image/similar?{}", serde_url_params::to_string(&SimilarImageQuery { url: image_url, page: Some(page + 1) }).map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?)) {
+                        "More results"
+                    }
+                }
+            }
+
+            footer class="bg-neutral-100 grid dark:bg-zinc-900 grid-cols-3 w-full px-4 py-2 gap-2" {
+                a href="https://dryicons.com/icon/search-2621" {"Icon by Dryicons"}
+            }
+        }
+    }))
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ViewData {
     page: u32,
@@ -302,6 +802,8 @@ pub async fn image_view(
                         query: search_query.query.clone(),
                         page: view_data.page,
                     }),
+                    color: image_filters(search_query.image_params.as_ref()).0,
+                    brightness: image_filters(search_query.image_params.as_ref()).1 as i32,
                     size: search_query.image_params.as_ref().and_then(|params| {
                         params
                             .size_range
@@ -358,9 +860,11 @@ pub async fn image_view(
                 div id="image-view" class="flex flex-col border-l border-neutral-200 dark:border-zinc-700 transition-all flex-1 overflow-y-scroll overflow-x-hidden p-4" {
                     div class="flex flex-row items-center pb-8" {
                         div class="flex flex-1 flex-row items-center" {
-                            img class="self-center w-4 h-4 rounded-full mr-2 bg-white" src=(img.source.as_ref().unwrap().icon_url.as_deref().unwrap_or("/public/gloabe.svg")) alt=(img.alt_text.as_deref().unwrap_or_default()) {}
-                            span class="text" {
-                                (display_site_name(img.source.as_ref().unwrap()))
+                            @if let Some(source) = &img.source {
+                                img class="self-center w-4 h-4 rounded-full mr-2 bg-white" src=(source.icon_url.as_deref().unwrap_or("/public/gloabe.svg")) alt=(img.alt_text.as_deref().unwrap_or_default()) {}
+                                span class="text" {
+                                    (display_site_name(source))
+                                }
                             }
                         }
                         div class="flex flex-row pl-8 items-center font-semibold" {
@@ -370,14 +874,24 @@ pub async fn image_view(
                         }
                     }
 
-                    img class="self-center m-2 w-full rounded bg-white" src=(img.url) alt=(img.alt_text.as_deref().unwrap_or_default()) {}
+                    a href=(img.full_url.as_deref().unwrap_or(&img.url)) class="self-center m-2 w-full" {
+                        img class="w-full rounded bg-white" src=(format!("/image/proxy/{}", img.id)) alt=(img.alt_text.as_deref().unwrap_or_default()) {}
+                    }
 
                     div class="flex flex-row pt-4 items-center" {
-                        span class="flex-1" {
-                            (img.alt_text())
+                        div class="flex-1 flex flex-col" {
+                            span { (img.alt_text()) }
+                            @if img.page_count > 1 {
+                                span class="text-sm text-neutral-500 dark:text-zinc-400" { (display_page_count(img.page_count)) }
+                            }
+                        }
+                        a href=(similar_image_url(&img.url)) class="px-2 py-1 mr-2 rounded-xl bg-neutral-200 dark:bg-zinc-700 font-semibold self-start h-fit" {
+                            "Similar"
                         }
-                        a href=(img.source.as_ref().unwrap().url) class="px-2 py-1 rounded-xl bg-sky-200 text-black font-semibold self-start h-fit" {
-                            "Visit >"
+                        @if let Some(source) = &img.source {
+                            a href=(source.url) class="px-2 py-1 rounded-xl bg-sky-200 text-black font-semibold self-start h-fit" {
+                                "Visit >"
+                            }
                         }
                     }
                 }
@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use axum::{
     extract::State,
-    http::{HeaderMap, HeaderName, StatusCode, Uri},
+    http::{header::ACCEPT_LANGUAGE, HeaderMap, HeaderName, StatusCode, Uri},
     Form,
 };
 use maud::{html, Markup, DOCTYPE};
@@ -52,6 +52,126 @@ pub fn search_bar(query: &str) -> Markup {
     }
 }
 
+/// Reverse image search by file upload or by image URL.
+pub fn similar_image_form() -> Markup {
+    html! {
+        details class="mx-4 text-sm" {
+            summary class="cursor-pointer select-none" { "Search by image" }
+            div class="flex flex-col gap-2 pt-2" {
+                form action="/image/similar" method="post" enctype="multipart/form-data" class="flex flex-row items-center gap-2" {
+                    input type="file" name="image" accept="image/*" required {}
+                    button type="submit" class="px-2 py-1 rounded-xl bg-sky-200 text-black font-semibold" { "Upload" }
+                }
+                form action="/image/similar" method="get" class="flex flex-row items-center gap-2" {
+                    input type="url" name="url" placeholder="Image URL" required
+                        class="min-w-0 px-2 py-1 border-black border rounded-xl dark:bg-zinc-800 dark:border-zinc-700" {}
+                    button type="submit" class="px-2 py-1 rounded-xl bg-sky-200 text-black font-semibold" { "Search" }
+                }
+            }
+        }
+    }
+}
+
+/// Colors offered in the image search color filter, matching the crawler's color names.
+pub const IMAGE_COLORS: &[(&str, &str)] = &[
+    ("red", "Red"),
+    ("orange", "Orange"),
+    ("yellow", "Yellow"),
+    ("green", "Green"),
+    ("teal", "Teal"),
+    ("blue", "Blue"),
+    ("purple", "Purple"),
+    ("pink", "Pink"),
+    ("brown", "Brown"),
+    ("black", "Black"),
+    ("gray", "Gray"),
+    ("white", "White"),
+];
+
+pub fn image_filter_select(color: Option<&str>, brightness: Option<&str>) -> Markup {
+    let color = color.unwrap_or_default();
+    let brightness = brightness.unwrap_or_default();
+
+    html! {
+        select name="color" onchange="this.form.requestSubmit()" class="ml-4 px-2 py-2 border-black border rounded-xl bg-transparent
+            dark:bg-zinc-800 dark:border-zinc-700" {
+            option value="" selected[color.is_empty()] { "Any color" }
+            @for (value, name) in IMAGE_COLORS {
+                option value=(value) selected[color == *value] { (name) }
+            }
+        }
+        select name="brightness" onchange="this.form.requestSubmit()" class="ml-4 px-2 py-2 border-black border rounded-xl bg-transparent
+            dark:bg-zinc-800 dark:border-zinc-700" {
+            option value="" selected[brightness.is_empty()] { "Any brightness" }
+            option value="dark" selected[brightness == "dark"] { "Dark" }
+            option value="light" selected[brightness == "light"] { "Light" }
+        }
+    }
+}
+
+/// Languages offered in the web search language picker, as ISO 639-1 codes.
+pub const LANGUAGES: &[(&str, &str)] = &[
+    ("en", "English"),
+    ("de", "Deutsch"),
+    ("es", "Español"),
+    ("fr", "Français"),
+    ("it", "Italiano"),
+    ("nl", "Nederlands"),
+    ("pl", "Polski"),
+    ("pt", "Português"),
+    ("ru", "Русский"),
+    ("ja", "日本語"),
+    ("ko", "한국어"),
+    ("zh", "中文"),
+];
+
+/// Value of the `language` query param that disables language filtering.
+pub const ANY_LANGUAGE: &str = "any";
+
+/// Returns the primary subtag of the highest weighted `Accept-Language` entry.
+pub fn preferred_language(headers: &HeaderMap) -> Option<String> {
+    let accept_language = headers.get(ACCEPT_LANGUAGE)?.to_str().ok()?;
+
+    accept_language
+        .split(',')
+        .filter_map(|entry| {
+            let mut parts = entry.split(';');
+            let tag = parts.next()?.trim();
+            let quality = parts
+                .find_map(|param| param.trim().strip_prefix("q="))
+                .map(|q| q.parse::<f32>().unwrap_or(0.0))
+                .unwrap_or(1.0);
+
+            (tag != "*" && !tag.is_empty() && quality > 0.0).then_some((tag, quality))
+        })
+        .fold(
+            None,
+            |best: Option<(&str, f32)>, (tag, quality)| match best {
+                Some((_, best_quality)) if best_quality >= quality => best,
+                _ => Some((tag, quality)),
+            },
+        )
+        .and_then(|(tag, _)| tag.split('-').next())
+        .map(|language| language.to_ascii_lowercase())
+}
+
+pub fn language_select(selected: Option<&str>) -> Markup {
+    let selected = selected.unwrap_or(ANY_LANGUAGE);
+
+    html! {
+        select name="language" onchange="this.form.requestSubmit()" class="ml-4 px-2 py-2 border-black border rounded-xl bg-transparent
+            dark:bg-zinc-800 dark:border-zinc-700" {
+            option value=(ANY_LANGUAGE) selected[selected == ANY_LANGUAGE] { "Any language" }
+            @for (code, name) in LANGUAGES {
+                option value=(code) selected[selected == *code] { (name) }
+            }
+            @if selected != ANY_LANGUAGE && !LANGUAGES.iter().any(|(code, _)| *code == selected) {
+                option value=(selected) selected { (selected) }
+            }
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct SearchSuggestionQuery {
     query: String,
@@ -96,6 +216,8 @@ pub async fn search_suggestions(
 
     let search_url = match current_url.path().split('/').nth(1) {
         Some("image") => "/image/search",
+        Some("video") => "/video/search",
+        Some("audio") => "/audio/search",
         _ => "/search",
     };
 
@@ -104,7 +226,8 @@ pub async fn search_suggestions(
             @let search_params = serde_url_params::to_string(&SearchQuery {
                 query: possibility.clone(),
                 page: None,
-                image_params: search_query.as_ref().and_then(|query| query.image_params.as_ref().and_then(|params| params.size_range.as_ref().map(|range| ImageParams { size_range: Some(range.clone()), selected:None }))),
+                language: search_query.as_ref().and_then(|query| query.language.clone()),
+                image_params: search_query.as_ref().and_then(|query| query.image_params.as_ref().map(ImageParams::filters)),
             })
             .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
 
@@ -0,0 +1,19 @@
+use image::{imageops::FilterType, DynamicImage};
+
+/// Difference hash of an image: the image is shrunk to 9x8 grayscale and each bit
+/// records whether a pixel is brighter than its right neighbour. Visually similar
+/// images differ in only a few bits, so hashes are compared by Hamming distance.
+pub fn dhash(img: &DynamicImage) -> u64 {
+    let small = img.resize_exact(9, 8, FilterType::Triangle).into_luma8();
+
+    let mut hash = 0;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash
+}
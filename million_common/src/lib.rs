@@ -0,0 +1,5 @@
+//! Helpers shared by the crawler, backend and client that have no place in the wire
+//! types of `proto`.
+
+pub mod image_hash;
+pub mod net;
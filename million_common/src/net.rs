@@ -0,0 +1,346 @@
+use std::{
+    fmt,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use ipnet::IpNet;
+use reqwest::{
+    dns::{Addrs, Name, Resolve, Resolving},
+    redirect::Policy,
+    Client, Response,
+};
+use url::{Host, Url};
+
+/// Keeps outgoing requests from reaching into the network they are sent from. Hosts are
+/// checked after DNS resolution, so a public name pointing at a private address is
+/// blocked too.
+#[derive(Debug, Clone, Default)]
+pub struct AddressFilter {
+    /// Networks that may be reached even though they are internal, e.g. an intranet.
+    allowed: Arc<Vec<IpNet>>,
+}
+
+impl AddressFilter {
+    pub fn new(allowed: Vec<IpNet>) -> Self {
+        Self {
+            allowed: Arc::new(allowed),
+        }
+    }
+
+    pub fn is_allowed(&self, ip: IpAddr) -> bool {
+        !is_internal(ip) || self.allowed.iter().any(|network| network.contains(&ip))
+    }
+
+    /// Rejects URLs whose host is a blocked IP literal, which never reach the resolver.
+    pub fn check_url(&self, url: &Url) -> Result<(), BlockedAddress> {
+        let ip = match url.host() {
+            Some(Host::Ipv4(ip)) => IpAddr::V4(ip),
+            Some(Host::Ipv6(ip)) => IpAddr::V6(ip),
+            _ => return Ok(()),
+        };
+
+        if self.is_allowed(ip) {
+            Ok(())
+        } else {
+            Err(BlockedAddress {
+                host: ip.to_string(),
+            })
+        }
+    }
+
+    /// Checks `url` for a client that resolves hosts itself, like a browser: the host has
+    /// to resolve, and only to allowed addresses.
+    pub async fn check_resolved(&self, url: &Url) -> Result<(), BlockedAddress> {
+        self.check_url(url)?;
+
+        let Some(Host::Domain(host)) = url.host() else {
+            return Ok(());
+        };
+        let blocked = || BlockedAddress {
+            host: host.to_owned(),
+        };
+
+        let addrs = tokio::net::lookup_host((host, 0))
+            .await
+            .map_err(|_| blocked())?
+            .collect::<Vec<SocketAddr>>();
+
+        if addrs.is_empty() || !addrs.iter().all(|addr| self.is_allowed(addr.ip())) {
+            return Err(blocked());
+        }
+
+        Ok(())
+    }
+
+    /// A client for URLs that come from users or crawled pages: it resolves through this
+    /// filter, checks every redirect target, and gives up after `timeout`.
+    pub fn client(&self, timeout: Duration, max_redirects: usize) -> reqwest::Result<Client> {
+        let filter = self.clone();
+        let redirect = Policy::custom(move |attempt| {
+            if attempt.previous().len() > max_redirects {
+                attempt.error("too many redirects")
+            } else if let Err(err) = filter.check_url(attempt.url()) {
+                attempt.error(err)
+            } else {
+                attempt.follow()
+            }
+        });
+
+        Client::builder()
+            .timeout(timeout)
+            .redirect(redirect)
+            .dns_resolver(Arc::new(self.clone()))
+            .build()
+    }
+}
+
+impl Resolve for AddressFilter {
+    fn resolve(&self, name: Name) -> Resolving {
+        let filter = self.clone();
+
+        Box::pin(async move {
+            let host = name.as_str();
+            let addrs = tokio::net::lookup_host((host, 0))
+                .await?
+                .filter(|addr| filter.is_allowed(addr.ip()))
+                .collect::<Vec<SocketAddr>>();
+
+            if addrs.is_empty() {
+                return Err(BlockedAddress {
+                    host: host.to_owned(),
+                }
+                .into());
+            }
+
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// A request was refused because its host is, or only resolves to, an internal address.
+#[derive(Debug)]
+pub struct BlockedAddress {
+    pub host: String,
+}
+
+impl fmt::Display for BlockedAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is an internal address", self.host)
+    }
+}
+
+impl std::error::Error for BlockedAddress {}
+
+/// A response body grew past the size its reader allowed.
+#[derive(Debug)]
+pub struct TooLarge {
+    pub url: String,
+    pub max_bytes: usize,
+}
+
+impl fmt::Display for TooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is larger than {} bytes", self.url, self.max_bytes)
+    }
+}
+
+impl std::error::Error for TooLarge {}
+
+/// Reads a response body, failing with [`TooLarge`] as soon as it grows past `max_bytes`.
+pub async fn read_capped(mut res: Response, max_bytes: usize) -> anyhow::Result<Vec<u8>> {
+    let too_large = |res: &Response| TooLarge {
+        url: res.url().to_string(),
+        max_bytes,
+    };
+
+    if res
+        .content_length()
+        .is_some_and(|length| length > max_bytes as u64)
+    {
+        return Err(too_large(&res).into());
+    }
+
+    let mut bytes = Vec::new();
+    while let Some(chunk) = res.chunk().await? {
+        if bytes.len() + chunk.len() > max_bytes {
+            return Err(too_large(&res).into());
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+
+    Ok(bytes)
+}
+
+/// Loopback, private, link-local (including cloud metadata endpoints), shared,
+/// multicast and unspecified addresses. IPv6 addresses that carry an IPv4 address are
+/// judged by the address they carry.
+pub fn is_internal(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_internal_v4(ip),
+        IpAddr::V6(ip) => match embedded_ipv4(ip) {
+            Some(ip) => is_internal_v4(ip),
+            None => is_internal_v6(ip),
+        },
+    }
+}
+
+/// The IPv4 address inside an IPv4-mapped (`::ffff:0:0/96`), IPv4-compatible (`::/96`),
+/// NAT64 (`64:ff9b::/96`), 6to4 (`2002::/16`) or Teredo (`2001::/32`) address, all of
+/// which end up at that IPv4 address.
+fn embedded_ipv4(ip: Ipv6Addr) -> Option<Ipv4Addr> {
+    let segments = ip.segments();
+    let [.., a, b, c, d] = ip.octets();
+
+    if let Some(ip) = ip.to_ipv4_mapped() {
+        Some(ip)
+    } else if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] || segments[..6] == [0; 6] {
+        Some(Ipv4Addr::new(a, b, c, d))
+    } else if segments[..2] == [0x2001, 0] {
+        // Teredo stores the client's address with every bit flipped.
+        Some(Ipv4Addr::new(!a, !b, !c, !d))
+    } else if segments[0] == 0x2002 {
+        let [a, b] = segments[1].to_be_bytes();
+        let [c, d] = segments[2].to_be_bytes();
+        Some(Ipv4Addr::new(a, b, c, d))
+    } else {
+        None
+    }
+}
+
+fn is_internal_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+
+    ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        // 0.0.0.0/8, "this network".
+        || a == 0
+        // 100.64.0.0/10, carrier-grade NAT, also used for some metadata services.
+        || (a == 100 && (b & 0b1100_0000) == 64)
+        // 192.0.0.0/24, IETF protocol assignments.
+        || (a == 192 && b == 0 && c == 0)
+        // 198.18.0.0/15, benchmarking.
+        || (a == 198 && (b & 0b1111_1110) == 18)
+        // 240.0.0.0/4, reserved.
+        || a >= 240
+}
+
+fn is_internal_v6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+
+    ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        // fc00::/7, unique local.
+        || (first & 0xfe00) == 0xfc00
+        // fe80::/10, link-local, and the deprecated fec0::/10 site-local.
+        || (first & 0xffc0) == 0xfe80
+        || (first & 0xffc0) == 0xfec0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_addresses() {
+        let cases = [
+            // IPv4
+            ("8.8.8.8", false),
+            ("93.184.216.34", false),
+            ("127.0.0.1", true),
+            ("10.1.2.3", true),
+            ("172.16.0.1", true),
+            ("192.168.1.1", true),
+            ("169.254.169.254", true),
+            ("100.64.0.1", true),
+            ("100.128.0.1", false),
+            ("198.18.0.1", true),
+            ("0.1.2.3", true),
+            ("224.0.0.1", true),
+            ("255.255.255.255", true),
+            ("192.0.0.8", true),
+            ("192.0.1.1", false),
+            ("240.0.0.1", true),
+            ("239.255.255.255", true),
+            // IPv6
+            ("2606:4700::1111", false),
+            ("::1", true),
+            ("::", true),
+            ("fd00::1", true),
+            ("fe80::1", true),
+            ("fec0::1", true),
+            ("ff02::1", true),
+            // IPv4-mapped
+            ("::ffff:8.8.8.8", false),
+            ("::ffff:127.0.0.1", true),
+            ("::ffff:169.254.169.254", true),
+            // NAT64
+            ("64:ff9b::8.8.8.8", false),
+            ("64:ff9b::a9fe:a9fe", true),
+            ("64:ff9b::10.0.0.1", true),
+            // 6to4
+            ("2002:0808:0808::1", false),
+            ("2002:a9fe:a9fe::1", true),
+            ("2002:7f00:0001::", true),
+            // IPv4-compatible
+            ("::7f00:1", true),
+            ("::a9fe:a9fe", true),
+            ("::808:808", false),
+            // Teredo, with the client address inverted
+            ("2001:0:4136:e378:8000:63bf:80ff:fffe", true),
+            ("2001:0:4136:e378:8000:63bf:f7f7:f7f7", false),
+        ];
+
+        for (ip, internal) in cases {
+            assert_eq!(is_internal(ip.parse().unwrap()), internal, "{}", ip);
+        }
+    }
+
+    #[test]
+    fn allowed_networks_open_internal_ranges() {
+        let filter = AddressFilter::new(vec![
+            "10.0.0.0/8".parse().unwrap(),
+            "fd00::/8".parse().unwrap(),
+        ]);
+
+        let cases = [
+            ("10.1.2.3", true),
+            ("8.8.8.8", true),
+            ("192.168.1.1", false),
+            ("169.254.169.254", false),
+            ("fd00::1", true),
+            ("fc00::1", false),
+        ];
+
+        for (ip, allowed) in cases {
+            assert_eq!(filter.is_allowed(ip.parse().unwrap()), allowed, "{}", ip);
+        }
+    }
+
+    #[test]
+    fn checks_ip_literal_urls() {
+        let filter = AddressFilter::default();
+
+        assert!(filter
+            .check_url(&"http://example.com/".parse().unwrap())
+            .is_ok());
+        assert!(filter
+            .check_url(&"http://8.8.8.8/".parse().unwrap())
+            .is_ok());
+        assert!(filter
+            .check_url(&"http://169.254.169.254/latest/".parse().unwrap())
+            .is_err());
+        assert!(filter
+            .check_url(&"http://[::ffff:127.0.0.1]:8080/".parse().unwrap())
+            .is_err());
+        assert!(filter
+            .check_url(&"http://[64:ff9b::a9fe:a9fe]/".parse().unwrap())
+            .is_err());
+    }
+}
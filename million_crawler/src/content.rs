@@ -0,0 +1,487 @@
+use std::collections::HashMap;
+
+use ego_tree::{NodeId, NodeRef};
+use scraper::{ElementRef, Html, Node};
+
+/// Elements that never hold page content.
+const IGNORED_TAGS: &[&str] = &[
+    "script", "style", "noscript", "template", "nav", "header", "footer", "aside", "form",
+    "button", "select", "iframe", "svg", "canvas", "dialog", "menu",
+];
+
+/// Elements whose text is collected as a paragraph when they contain no other block.
+const BLOCK_TAGS: &[&str] = &[
+    "p",
+    "li",
+    "td",
+    "dd",
+    "dt",
+    "blockquote",
+    "pre",
+    "figcaption",
+    "div",
+    "section",
+    "article",
+    "main",
+];
+
+const HEADING_TAGS: &[&str] = &["h1", "h2", "h3", "h4", "h5", "h6"];
+
+/// Class and id fragments that mark boilerplate even next to a positive hint.
+const UNLIKELY_HINTS: &[&str] = &[
+    "cookie",
+    "consent",
+    "advert",
+    "sponsor",
+    "popup",
+    "modal",
+    "newsletter",
+    "subscribe",
+    "share",
+    "social",
+];
+
+/// Class and id fragments that mark boilerplate such as banners and sidebars.
+const NEGATIVE_HINTS: &[&str] = &[
+    "banner",
+    "nav",
+    "menu",
+    "footer",
+    "header",
+    "sidebar",
+    "comment",
+    "promo",
+    "breadcrumb",
+    "related",
+    "masthead",
+    "widget",
+];
+
+/// Class and id fragments that mark the main article.
+const POSITIVE_HINTS: &[&str] = &[
+    "article", "content", "main", "post", "entry", "text", "body", "story", "blog",
+];
+
+/// Blocks shorter than this do not contribute to their ancestors' scores.
+const MIN_BLOCK_LENGTH: usize = 25;
+
+/// Below this much text the winning candidate is not trusted and callers should fall back.
+const MIN_CONTENT_LENGTH: usize = 140;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct MainContent {
+    pub text_fields: Vec<String>,
+    pub sections: Vec<String>,
+}
+
+/// Finds the block of the document most likely to be the main article and returns its
+/// paragraphs and headings, or `None` if no block stands out from the boilerplate.
+pub fn extract_main_content(doc: &Html) -> Option<MainContent> {
+    let lengths = text_lengths(doc);
+
+    let mut pieces = Vec::new();
+    walk_pieces(doc.root_element(), &lengths, &mut pieces);
+
+    let mut scores: HashMap<_, f64> = HashMap::new();
+
+    for piece in pieces {
+        let Piece::Paragraph { parent, nodes } = piece else {
+            continue;
+        };
+
+        let text = paragraph_text(&nodes);
+        let length = text.chars().count();
+
+        if length < MIN_BLOCK_LENGTH {
+            continue;
+        }
+
+        let score = 1.0 + text.matches(',').count() as f64 + f64::min(length as f64 / 100.0, 3.0);
+
+        for (level, ancestor) in std::iter::once(parent)
+            .chain(parent.ancestors().filter_map(ElementRef::wrap))
+            .take(3)
+            .enumerate()
+        {
+            let divider = match level {
+                0 => 1.0,
+                1 => 2.0,
+                level => level as f64 * 3.0,
+            };
+
+            *scores
+                .entry(ancestor.id())
+                .or_insert_with(|| initial_score(ancestor)) += score / divider;
+        }
+    }
+
+    let mut top = scores
+        .into_iter()
+        .filter_map(|(id, score)| {
+            let element = ElementRef::wrap(doc.tree.get(id)?)?;
+            let length = lengths.get(&id).copied().unwrap_or_default();
+            Some((element, score * (1.0 - length.link_density())))
+        })
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(element, _)| element)?;
+
+    // Climb to wrappers that add little text of their own, so the article's title and
+    // intro are kept when they sit just outside the highest scoring block.
+    while let Some(parent) = top.parent().and_then(ElementRef::wrap) {
+        if matches!(parent.value().name(), "body" | "html") || is_boilerplate(parent) {
+            break;
+        }
+
+        let text = |element: ElementRef| lengths.get(&element.id()).map_or(0, |length| length.text);
+        if text(parent) * 4 > text(top) * 5 {
+            break;
+        }
+
+        top = parent;
+    }
+
+    let mut pieces = Vec::new();
+    walk_pieces(top, &lengths, &mut pieces);
+
+    let mut content = MainContent::default();
+
+    for piece in pieces {
+        match piece {
+            Piece::Heading(heading) => {
+                let heading = collect_text(heading);
+                if !heading.is_empty() {
+                    content.sections.push(heading);
+                }
+            }
+            Piece::Paragraph { nodes, .. } => {
+                let length = nodes
+                    .iter()
+                    .map(|node| node_length(*node, &lengths))
+                    .fold(TextLength::default(), TextLength::add);
+                if length.link_density() >= 0.5 {
+                    continue;
+                }
+
+                let text = paragraph_text(&nodes);
+                if !text.is_empty() {
+                    content.text_fields.push(text);
+                }
+            }
+        }
+    }
+
+    let length: usize = content
+        .text_fields
+        .iter()
+        .map(|text| text.chars().count())
+        .sum();
+
+    (length >= MIN_CONTENT_LENGTH).then_some(content)
+}
+
+fn initial_score(element: ElementRef) -> f64 {
+    let tag_score = match element.value().name() {
+        "article" | "main" => 10.0,
+        "div" | "section" => 5.0,
+        "pre" | "td" | "blockquote" => 3.0,
+        "ol" | "ul" | "dl" | "dd" | "dt" | "li" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+        _ => 0.0,
+    };
+
+    tag_score + class_weight(element)
+}
+
+fn class_weight(element: ElementRef) -> f64 {
+    let hints = hints(element);
+
+    let mut weight = 0.0;
+    if POSITIVE_HINTS.iter().any(|hint| hints.contains(hint)) {
+        weight += 25.0;
+    }
+    if UNLIKELY_HINTS
+        .iter()
+        .chain(NEGATIVE_HINTS)
+        .any(|hint| hints.contains(hint))
+    {
+        weight -= 25.0;
+    }
+    weight
+}
+
+fn hints(element: ElementRef) -> String {
+    let value = element.value();
+    format!(
+        "{} {} {}",
+        value.attr("class").unwrap_or_default(),
+        value.id().unwrap_or_default(),
+        value.attr("role").unwrap_or_default(),
+    )
+    .to_ascii_lowercase()
+}
+
+/// Whether the element itself should be dropped as boilerplate.
+fn is_boilerplate(element: ElementRef) -> bool {
+    let value = element.value();
+
+    if IGNORED_TAGS.contains(&value.name()) || value.attr("hidden").is_some() {
+        return true;
+    }
+
+    if value.attr("aria-hidden") == Some("true") {
+        return true;
+    }
+
+    let hints = hints(element);
+    UNLIKELY_HINTS.iter().any(|hint| hints.contains(hint))
+        || (NEGATIVE_HINTS.iter().any(|hint| hints.contains(hint))
+            && !POSITIVE_HINTS.iter().any(|hint| hints.contains(hint)))
+}
+
+/// Text and linked text of an element's non-boilerplate content, counted as word
+/// characters plus one separator per word.
+#[derive(Debug, Default, Clone, Copy)]
+struct TextLength {
+    text: usize,
+    links: usize,
+    /// Whether a block or heading sits somewhere inside.
+    has_blocks: bool,
+}
+
+impl TextLength {
+    fn add(self, other: Self) -> Self {
+        Self {
+            text: self.text + other.text,
+            links: self.links + other.links,
+            has_blocks: self.has_blocks || other.has_blocks,
+        }
+    }
+
+    fn link_density(self) -> f64 {
+        if self.text == 0 {
+            0.0
+        } else {
+            self.links as f64 / self.text as f64
+        }
+    }
+}
+
+/// Lengths of every element outside boilerplate, computed in one pass from the leaves
+/// up. Boilerplate elements are left out and count as empty.
+fn text_lengths(doc: &Html) -> HashMap<NodeId, TextLength> {
+    let elements = doc
+        .root_element()
+        .descendants()
+        .filter_map(ElementRef::wrap)
+        .collect::<Vec<_>>();
+
+    let mut lengths = HashMap::new();
+
+    // Children come after their parent in document order, so walking backwards sees
+    // every child first.
+    for element in elements.into_iter().rev() {
+        if is_boilerplate(element) {
+            continue;
+        }
+
+        let mut length = element
+            .children()
+            .map(|child| {
+                let mut length = node_length(child, &lengths);
+                if let Some(child) = ElementRef::wrap(child) {
+                    length.has_blocks |=
+                        is_block_or_heading(child) && lengths.contains_key(&child.id());
+                }
+                length
+            })
+            .fold(TextLength::default(), TextLength::add);
+
+        if element.value().name() == "a" {
+            length.links = length.text;
+        }
+
+        lengths.insert(element.id(), length);
+    }
+
+    lengths
+}
+
+fn node_length(node: NodeRef<Node>, lengths: &HashMap<NodeId, TextLength>) -> TextLength {
+    match node.value().as_text() {
+        Some(text) => TextLength {
+            text: text
+                .split_whitespace()
+                .map(|word| word.chars().count() + 1)
+                .sum(),
+            ..Default::default()
+        },
+        None => lengths.get(&node.id()).copied().unwrap_or_default(),
+    }
+}
+
+fn is_block_or_heading(element: ElementRef) -> bool {
+    let name = element.value().name();
+    BLOCK_TAGS.contains(&name) || HEADING_TAGS.contains(&name)
+}
+
+/// A heading, or a run of text read as one paragraph: a block without blocks inside, or
+/// the inline content between the blocks of a block that has some.
+enum Piece<'a> {
+    Heading(ElementRef<'a>),
+    Paragraph {
+        /// The element the paragraph sits in.
+        parent: ElementRef<'a>,
+        nodes: Vec<NodeRef<'a, Node>>,
+    },
+}
+
+/// Splits the content of `element` into pieces in document order, skipping boilerplate.
+fn walk_pieces<'a>(
+    element: ElementRef<'a>,
+    lengths: &HashMap<NodeId, TextLength>,
+    pieces: &mut Vec<Piece<'a>>,
+) {
+    let keeps_inline_text = BLOCK_TAGS.contains(&element.value().name());
+    let mut run = Vec::new();
+
+    let end_run = |run: &mut Vec<NodeRef<'a, Node>>, pieces: &mut Vec<Piece<'a>>| {
+        if keeps_inline_text && !run.is_empty() {
+            pieces.push(Piece::Paragraph {
+                parent: element,
+                nodes: std::mem::take(run),
+            });
+        }
+        run.clear();
+    };
+
+    for child in element.children() {
+        let Some(child_element) = ElementRef::wrap(child) else {
+            run.push(child);
+            continue;
+        };
+        let Some(length) = lengths.get(&child.id()) else {
+            // Boilerplate.
+            continue;
+        };
+
+        if HEADING_TAGS.contains(&child_element.value().name()) {
+            end_run(&mut run, pieces);
+            pieces.push(Piece::Heading(child_element));
+        } else if length.has_blocks {
+            end_run(&mut run, pieces);
+            walk_pieces(child_element, lengths, pieces);
+        } else if BLOCK_TAGS.contains(&child_element.value().name()) {
+            end_run(&mut run, pieces);
+            pieces.push(Piece::Paragraph {
+                parent: element,
+                nodes: vec![child],
+            });
+        } else {
+            run.push(child);
+        }
+    }
+
+    end_run(&mut run, pieces);
+}
+
+/// Text of the nodes with whitespace collapsed, skipping boilerplate.
+fn paragraph_text(nodes: &[NodeRef<Node>]) -> String {
+    let mut words = Vec::new();
+    for node in nodes {
+        if let Some(text) = node.value().as_text() {
+            words.extend(text.split_whitespace());
+        } else if let Some(element) = ElementRef::wrap(*node) {
+            if !is_boilerplate(element) {
+                push_words(element, &mut words);
+            }
+        }
+    }
+    words.join(" ")
+}
+
+/// Text of the element with whitespace collapsed, skipping ignored descendants.
+fn collect_text(element: ElementRef) -> String {
+    let mut words = Vec::new();
+    push_words(element, &mut words);
+    words.join(" ")
+}
+
+fn push_words<'a>(element: ElementRef<'a>, words: &mut Vec<&'a str>) {
+    for child in element.children() {
+        if let Some(text) = child.value().as_text() {
+            words.extend(text.split_whitespace());
+        } else if let Some(child) = ElementRef::wrap(child) {
+            if !is_boilerplate(child) {
+                push_words(child, words);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extract(html: &str) -> Option<MainContent> {
+        extract_main_content(&Html::parse_document(html))
+    }
+
+    #[test]
+    fn drops_navigation_banners_and_footers() {
+        let content = extract(include_str!("../tests/fixtures/content/blog_post.html")).unwrap();
+
+        assert_eq!(
+            content.sections,
+            vec!["Growing tomatoes on a balcony", "Choosing a container"]
+        );
+        assert_eq!(content.text_fields.len(), 4);
+        assert!(content.text_fields[0].starts_with("Tomatoes are one of the easiest"));
+
+        let all_text = content.text_fields.join(" ");
+        assert!(!all_text.contains("cookies"));
+        assert!(!all_text.contains("Subscribe"));
+        assert!(!all_text.contains("All rights reserved"));
+    }
+
+    #[test]
+    fn keeps_text_outside_paragraphs() {
+        let content = extract(include_str!("../tests/fixtures/content/div_article.html")).unwrap();
+
+        assert_eq!(content.sections, vec!["Release notes"]);
+        assert_eq!(
+            content.text_fields,
+            vec![
+                "Version 2.0 rewrites the storage engine, which makes writes roughly twice as fast on spinning disks.",
+                "The configuration format is now TOML, and the old INI files are converted automatically on first start.",
+                "Plugins built against 1.x need to be recompiled, because the plugin ABI changed.",
+                "Support for 32-bit platforms has been dropped, as nobody has run the test suite on them for years.",
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_inline_text_between_blocks() {
+        let content =
+            extract(include_str!("../tests/fixtures/content/mixed_content.html")).unwrap();
+
+        assert_eq!(content.sections, vec!["Keeping a sourdough starter"]);
+        assert_eq!(
+            content.text_fields,
+            vec![
+                "A starter is only flour and water, left somewhere warm until wild yeast moves in.",
+                "Feed it once a day, discarding half before every feeding, so the acidity stays in check.",
+                "If you bake rarely, keep it in the fridge and feed it once a week instead.",
+                "A healthy starter doubles within a few hours of feeding, and smells sour rather than sharp.",
+                "See the basic loaf for a first bake.",
+            ]
+        );
+    }
+
+    #[test]
+    fn returns_none_without_main_content() {
+        assert_eq!(
+            extract(include_str!("../tests/fixtures/content/link_hub.html")),
+            None
+        );
+    }
+}
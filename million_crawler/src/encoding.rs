@@ -0,0 +1,302 @@
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252, X_USER_DEFINED};
+
+/// How far into a document the `<meta>` prescan looks, as in the HTML standard.
+const PRESCAN_BYTES: usize = 1024;
+
+/// Picks the encoding of an HTML document the way browsers do: a byte order mark wins,
+/// then the `Content-Type` charset, then a `<meta>` declaration near the start of the
+/// document. Undeclared documents are read as UTF-8 when they are valid UTF-8 and as
+/// windows-1252 otherwise.
+pub fn sniff_html_encoding(bytes: &[u8], content_type: Option<&str>) -> &'static Encoding {
+    bom_encoding(bytes)
+        .or_else(|| content_type.and_then(header_encoding))
+        .or_else(|| prescan_meta(bytes))
+        .unwrap_or_else(|| fallback_encoding(bytes))
+}
+
+/// Like [`sniff_html_encoding`], for formats that can only declare their encoding
+/// through a byte order mark or the `Content-Type` header.
+pub fn sniff_text_encoding(bytes: &[u8], content_type: Option<&str>) -> &'static Encoding {
+    bom_encoding(bytes)
+        .or_else(|| content_type.and_then(header_encoding))
+        .unwrap_or_else(|| fallback_encoding(bytes))
+}
+
+fn bom_encoding(bytes: &[u8]) -> Option<&'static Encoding> {
+    Encoding::for_bom(bytes).map(|(encoding, _)| encoding)
+}
+
+fn header_encoding(content_type: &str) -> Option<&'static Encoding> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        if !name.trim().eq_ignore_ascii_case("charset") {
+            return None;
+        }
+        Encoding::for_label(value.trim().trim_matches('"').as_bytes())
+    })
+}
+
+fn fallback_encoding(bytes: &[u8]) -> &'static Encoding {
+    if std::str::from_utf8(bytes).is_ok() {
+        UTF_8
+    } else {
+        WINDOWS_1252
+    }
+}
+
+/// The HTML standard's "prescan a byte stream to determine its encoding".
+fn prescan_meta(bytes: &[u8]) -> Option<&'static Encoding> {
+    let bytes = &bytes[..bytes.len().min(PRESCAN_BYTES)];
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        let rest = &bytes[pos..];
+
+        if rest.starts_with(b"<!--") {
+            pos += find(&rest[4..], b"-->").map_or(rest.len(), |end| 4 + end + 3);
+        } else if starts_with_ignore_case(rest, b"<meta")
+            && rest.get(5).is_some_and(|&b| is_space(b) || b == b'/')
+        {
+            pos += 5;
+            if let Some(encoding) = meta_encoding(bytes, &mut pos) {
+                return Some(encoding);
+            }
+        } else if rest.starts_with(b"<")
+            && (rest.get(1).is_some_and(u8::is_ascii_alphabetic)
+                || (rest.get(1) == Some(&b'/') && rest.get(2).is_some_and(u8::is_ascii_alphabetic)))
+        {
+            pos += rest
+                .iter()
+                .position(|&b| is_space(b) || b == b'>')
+                .unwrap_or(rest.len());
+            while get_attribute(bytes, &mut pos).is_some() {}
+        } else if rest.starts_with(b"<!") || rest.starts_with(b"</") || rest.starts_with(b"<?") {
+            pos += rest
+                .iter()
+                .position(|&b| b == b'>')
+                .map_or(rest.len(), |end| end + 1);
+        } else {
+            pos += 1;
+        }
+    }
+
+    None
+}
+
+/// Reads the attributes of a `<meta>` tag and returns the encoding it declares, if any.
+fn meta_encoding(bytes: &[u8], pos: &mut usize) -> Option<&'static Encoding> {
+    let mut seen = Vec::new();
+    let mut got_pragma = false;
+    let mut need_pragma = None;
+    let mut charset = None;
+
+    while let Some((name, value)) = get_attribute(bytes, pos) {
+        if seen.contains(&name) {
+            continue;
+        }
+
+        match name.as_slice() {
+            b"http-equiv" => got_pragma |= value == b"content-type",
+            b"content" if charset.is_none() => {
+                if let Some(encoding) = charset_from_content(&value) {
+                    charset = Some(encoding);
+                    need_pragma = Some(true);
+                }
+            }
+            b"charset" => {
+                charset = Encoding::for_label(&value);
+                need_pragma = Some(false);
+            }
+            _ => {}
+        }
+
+        seen.push(name);
+    }
+
+    let charset = match need_pragma? {
+        true if !got_pragma => return None,
+        _ => charset?,
+    };
+
+    // A page can't be in UTF-16 if its ASCII-compatible prescan just succeeded.
+    Some(if charset == UTF_16LE || charset == UTF_16BE {
+        UTF_8
+    } else if charset == X_USER_DEFINED {
+        WINDOWS_1252
+    } else {
+        charset
+    })
+}
+
+/// The HTML standard's "get an attribute". ASCII letters in both names and values are
+/// lowercased.
+fn get_attribute(bytes: &[u8], pos: &mut usize) -> Option<(Vec<u8>, Vec<u8>)> {
+    let at = |pos: usize| bytes.get(pos).copied();
+
+    while at(*pos).is_some_and(|b| is_space(b) || b == b'/') {
+        *pos += 1;
+    }
+    if at(*pos)? == b'>' {
+        return None;
+    }
+
+    let mut name = Vec::new();
+    loop {
+        let b = at(*pos)?;
+        match b {
+            b'=' if !name.is_empty() => break,
+            b if is_space(b) => {
+                while at(*pos).is_some_and(is_space) {
+                    *pos += 1;
+                }
+                if at(*pos)? != b'=' {
+                    return Some((name, Vec::new()));
+                }
+                break;
+            }
+            b'/' | b'>' => return Some((name, Vec::new())),
+            b => name.push(b.to_ascii_lowercase()),
+        }
+        *pos += 1;
+    }
+
+    // Skip the `=` and any whitespace after it.
+    *pos += 1;
+    while at(*pos).is_some_and(is_space) {
+        *pos += 1;
+    }
+
+    let mut value = Vec::new();
+    match at(*pos)? {
+        quote @ (b'"' | b'\'') => {
+            *pos += 1;
+            loop {
+                let b = at(*pos)?;
+                *pos += 1;
+                if b == quote {
+                    return Some((name, value));
+                }
+                value.push(b.to_ascii_lowercase());
+            }
+        }
+        b'>' => Some((name, value)),
+        _ => {
+            while let Some(b) = at(*pos) {
+                if is_space(b) || b == b'>' {
+                    break;
+                }
+                value.push(b.to_ascii_lowercase());
+                *pos += 1;
+            }
+            Some((name, value))
+        }
+    }
+}
+
+/// The HTML standard's "extracting a character encoding from a meta element", for
+/// `content="text/html; charset=..."`.
+fn charset_from_content(content: &[u8]) -> Option<&'static Encoding> {
+    let mut rest = content;
+
+    loop {
+        let start = find(rest, b"charset")?;
+        rest = &rest[start + b"charset".len()..];
+
+        let after_space = rest.iter().position(|&b| !is_space(b))?;
+        if rest[after_space] == b'=' {
+            rest = &rest[after_space + 1..];
+            break;
+        }
+    }
+
+    let rest = &rest[rest.iter().position(|&b| !is_space(b))?..];
+
+    let label = match rest[0] {
+        quote @ (b'"' | b'\'') => {
+            let end = rest[1..].iter().position(|&b| b == quote)?;
+            &rest[1..1 + end]
+        }
+        _ => {
+            let end = rest
+                .iter()
+                .position(|&b| is_space(b) || b == b';')
+                .unwrap_or(rest.len());
+            &rest[..end]
+        }
+    };
+
+    Encoding::for_label(label)
+}
+
+fn is_space(b: u8) -> bool {
+    matches!(b, b'\t' | b'\n' | b'\x0C' | b'\r' | b' ')
+}
+
+fn starts_with_ignore_case(bytes: &[u8], prefix: &[u8]) -> bool {
+    bytes.len() >= prefix.len() && bytes[..prefix.len()].eq_ignore_ascii_case(prefix)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use encoding_rs::{GBK, SHIFT_JIS};
+
+    use super::*;
+
+    #[test]
+    fn byte_order_mark_beats_declarations() {
+        let html = b"\xEF\xBB\xBF<meta charset=\"shift_jis\">";
+
+        assert_eq!(
+            sniff_html_encoding(html, Some("text/html; charset=gbk")),
+            UTF_8
+        );
+    }
+
+    #[test]
+    fn header_beats_meta() {
+        let html = b"<meta charset=\"shift_jis\">";
+
+        assert_eq!(
+            sniff_html_encoding(html, Some("text/html; charset=\"GBK\"")),
+            GBK
+        );
+    }
+
+    #[test]
+    fn reads_meta_charset_and_pragma() {
+        assert_eq!(
+            sniff_html_encoding(b"<!DOCTYPE html><html><head><META CHARSET=Shift_JIS>", None),
+            SHIFT_JIS
+        );
+        assert_eq!(
+            sniff_html_encoding(
+                b"<head><meta http-equiv=\"Content-Type\" content=\"text/html; charset=gbk\">",
+                None
+            ),
+            GBK
+        );
+        // Without the pragma a `content` attribute declares nothing.
+        assert_eq!(
+            sniff_html_encoding(b"<meta content=\"text/html; charset=gbk\">", None),
+            UTF_8
+        );
+    }
+
+    #[test]
+    fn ignores_meta_in_comments_and_attributes() {
+        let html = b"<!-- <meta charset=gbk> --><div title='<meta charset=gbk>'>\xE9t\xE9</div>";
+
+        assert_eq!(sniff_html_encoding(html, None), WINDOWS_1252);
+    }
+
+    #[test]
+    fn utf16_meta_means_utf8() {
+        assert_eq!(sniff_html_encoding(b"<meta charset=utf-16le>", None), UTF_8);
+    }
+}
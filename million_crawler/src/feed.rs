@@ -0,0 +1,142 @@
+use url::Url;
+
+#[derive(Debug, Default)]
+pub struct FeedDocument {
+    pub title: Option<String>,
+    pub items: Vec<FeedItem>,
+}
+
+#[derive(Debug)]
+pub struct FeedItem {
+    pub url: Url,
+    pub title: Option<String>,
+    /// Unix timestamp in seconds, from the item's published date or else its updated date.
+    pub published_at: Option<i64>,
+}
+
+/// Parses an RSS, Atom or JSON Feed document into its item links.
+pub fn parse_feed(bytes: &[u8], page_url: &Url) -> anyhow::Result<FeedDocument> {
+    let feed = feed_rs::parser::parse(bytes)?;
+
+    let items = feed
+        .entries
+        .into_iter()
+        .filter_map(|entry| {
+            let link = entry
+                .links
+                .iter()
+                .find(|link| link.rel.as_deref().unwrap_or("alternate") == "alternate")
+                .or(entry.links.first())?;
+
+            Some(FeedItem {
+                url: page_url.join(&link.href).ok()?,
+                title: entry
+                    .title
+                    .map(|title| title.content.trim().to_owned())
+                    .filter(|title| !title.is_empty()),
+                published_at: entry
+                    .published
+                    .or(entry.updated)
+                    .map(|published| published.timestamp()),
+            })
+        })
+        .collect();
+
+    Ok(FeedDocument {
+        title: feed.title.map(|title| title.content.trim().to_owned()),
+        items,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page_url() -> Url {
+        Url::parse("https://example.com/blog/feed.xml").unwrap()
+    }
+
+    #[test]
+    fn parses_rss() {
+        let rss = br#"<?xml version="1.0"?>
+            <rss version="2.0">
+              <channel>
+                <title> Example Blog </title>
+                <link>https://example.com/blog</link>
+                <item>
+                  <title>First post</title>
+                  <link>https://example.com/blog/first</link>
+                  <pubDate>Sat, 01 Jun 2024 12:00:00 GMT</pubDate>
+                </item>
+                <item>
+                  <title> </title>
+                  <link>/blog/second</link>
+                </item>
+                <item>
+                  <title>No link</title>
+                </item>
+              </channel>
+            </rss>"#;
+
+        let feed = parse_feed(rss, &page_url()).unwrap();
+
+        assert_eq!(feed.title.as_deref(), Some("Example Blog"));
+        assert_eq!(feed.items.len(), 2);
+
+        assert_eq!(feed.items[0].url.as_str(), "https://example.com/blog/first");
+        assert_eq!(feed.items[0].title.as_deref(), Some("First post"));
+        assert_eq!(feed.items[0].published_at, Some(1717243200));
+
+        assert_eq!(
+            feed.items[1].url.as_str(),
+            "https://example.com/blog/second"
+        );
+        assert_eq!(feed.items[1].title, None);
+        assert_eq!(feed.items[1].published_at, None);
+    }
+
+    #[test]
+    fn parses_atom() {
+        let atom = br#"<?xml version="1.0" encoding="utf-8"?>
+            <feed xmlns="http://www.w3.org/2005/Atom">
+              <title>Example Atom</title>
+              <id>urn:example</id>
+              <updated>2024-06-02T00:00:00Z</updated>
+              <entry>
+                <title>Updated only</title>
+                <id>urn:example:1</id>
+                <link rel="edit" href="https://example.com/edit/1"/>
+                <link rel="alternate" href="posts/1"/>
+                <updated>2024-06-01T12:00:00Z</updated>
+              </entry>
+              <entry>
+                <title>Published</title>
+                <id>urn:example:2</id>
+                <link href="https://example.com/posts/2"/>
+                <published>2024-05-01T00:00:00Z</published>
+                <updated>2024-06-01T00:00:00Z</updated>
+              </entry>
+            </feed>"#;
+
+        let feed = parse_feed(atom, &page_url()).unwrap();
+
+        assert_eq!(feed.title.as_deref(), Some("Example Atom"));
+        assert_eq!(feed.items.len(), 2);
+
+        // The alternate link is preferred, and relative links resolve against the feed.
+        assert_eq!(
+            feed.items[0].url.as_str(),
+            "https://example.com/blog/posts/1"
+        );
+        assert_eq!(feed.items[0].published_at, Some(1717243200));
+
+        // The published date wins over the updated one.
+        assert_eq!(feed.items[1].url.as_str(), "https://example.com/posts/2");
+        assert_eq!(feed.items[1].published_at, Some(1714521600));
+    }
+
+    #[test]
+    fn rejects_other_documents() {
+        assert!(parse_feed(b"<html><body>Not a feed</body></html>", &page_url()).is_err());
+    }
+}
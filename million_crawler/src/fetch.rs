@@ -0,0 +1,412 @@
+use std::{fmt, sync::Arc, time::Duration};
+
+use encoding_rs::Encoding;
+use ipnet::IpNet;
+use million_common::net::{read_capped, AddressFilter, BlockedAddress, TooLarge};
+use proto::crawler::Guard;
+use reqwest::{
+    header::{
+        HeaderMap, HeaderValue, ACCEPT, ACCEPT_LANGUAGE, CONTENT_TYPE, FROM, LOCATION, USER_AGENT,
+    },
+    redirect::Policy,
+    Client, Response,
+};
+use url::Url;
+
+use crate::{
+    encoding::{sniff_html_encoding, sniff_text_encoding},
+    warc::{StoredResponse, WarcReplay, WarcWriter},
+};
+
+/// `Accept` sent for image downloads instead of the page one in [`Identity`].
+const IMAGE_ACCEPT: &str = "image/avif,image/webp,image/svg+xml,image/*,*/*;q=0.8";
+
+/// Limits that keep one hostile or oversized response from taking down a worker.
+#[derive(clap::Args, Debug, Clone)]
+pub struct FetchLimits {
+    /// Largest page, PDF or feed body read, in bytes.
+    #[arg(long, env, default_value_t = 16 * 1024 * 1024)]
+    pub max_body_bytes: usize,
+
+    /// Largest image file read, in bytes.
+    #[arg(long, env, default_value_t = 8 * 1024 * 1024)]
+    pub max_image_bytes: usize,
+
+    /// Seconds allowed for a whole request, including reading the body.
+    #[arg(long, env, default_value_t = 30)]
+    pub request_timeout_secs: u64,
+
+    #[arg(long, env, default_value_t = 10)]
+    pub max_redirects: usize,
+
+    /// Largest width or height of an image that will be decoded.
+    #[arg(long, env, default_value_t = 8192)]
+    pub max_image_dimension: u32,
+
+    /// Most memory an image decoder may allocate, in bytes.
+    #[arg(long, env, default_value_t = 256 * 1024 * 1024)]
+    pub max_image_alloc_bytes: u64,
+
+    /// Seconds allowed for extracting the text of a PDF.
+    #[arg(long, env, default_value_t = 30)]
+    pub max_pdf_secs: u64,
+
+    /// Internal networks that may still be crawled, e.g. `10.1.0.0/16` for an intranet.
+    /// Private, loopback and link-local addresses are blocked otherwise.
+    #[arg(long, env, value_delimiter = ',')]
+    pub allowed_networks: Vec<IpNet>,
+}
+
+impl FetchLimits {
+    /// The filter every outgoing request is checked against, in the fetcher and the
+    /// browser alike.
+    pub fn address_filter(&self) -> AddressFilter {
+        AddressFilter::new(self.allowed_networks.clone())
+    }
+}
+
+/// How the crawler introduces itself to the sites it visits.
+#[derive(clap::Args, Debug, Clone)]
+pub struct Identity {
+    /// Product token in the `User-Agent`, which site operators match in robots.txt.
+    #[arg(long, env, default_value_t = String::from("MillionSearchBot"))]
+    pub bot_name: String,
+
+    #[arg(long, env, default_value_t = String::from(env!("CARGO_PKG_VERSION")))]
+    pub bot_version: String,
+
+    /// Public page explaining what the crawler is, linked from the `User-Agent`. There is
+    /// no default, since site operators have to be able to reach it.
+    #[arg(long, env)]
+    pub bot_info_url: Url,
+
+    /// Contact address sent in the `From` header.
+    #[arg(long, env)]
+    pub bot_from: Option<String>,
+
+    #[arg(
+        long,
+        env,
+        default_value_t = String::from("text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8")
+    )]
+    pub accept: String,
+
+    #[arg(long, env, default_value_t = String::from("en,*;q=0.5"))]
+    pub accept_language: String,
+}
+
+impl Identity {
+    pub fn user_agent(&self) -> String {
+        format!(
+            "Mozilla/5.0 (compatible; {}/{}; +{})",
+            self.bot_name, self.bot_version, self.bot_info_url
+        )
+    }
+
+    fn default_headers(&self) -> anyhow::Result<HeaderMap> {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT, HeaderValue::from_str(&self.accept)?);
+        headers.insert(
+            ACCEPT_LANGUAGE,
+            HeaderValue::from_str(&self.accept_language)?,
+        );
+        if let Some(from) = &self.bot_from {
+            headers.insert(FROM, HeaderValue::from_str(from)?);
+        }
+        Ok(headers)
+    }
+}
+
+/// A request stopped by one of the [`FetchLimits`].
+#[derive(Debug, Clone)]
+pub struct GuardError {
+    pub guard: Guard,
+    pub url: String,
+}
+
+impl fmt::Display for GuardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} stopped by {}", self.url, self.guard.as_str_name())
+    }
+}
+
+impl std::error::Error for GuardError {}
+
+/// The guard that stopped a job, if it failed because of one.
+pub fn tripped_guard(err: &anyhow::Error) -> Option<Guard> {
+    // Our own guards are often wrapped in a `reqwest::Error`, so look for them first.
+    if let Some(err) = err
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<GuardError>())
+    {
+        return Some(err.guard);
+    }
+    if err
+        .chain()
+        .any(|cause| cause.downcast_ref::<BlockedAddress>().is_some())
+    {
+        return Some(Guard::BlockedAddress);
+    }
+
+    err.chain().find_map(|cause| {
+        let err = cause.downcast_ref::<reqwest::Error>()?;
+        if err.is_timeout() {
+            Some(Guard::Timeout)
+        } else if err.is_redirect() {
+            Some(Guard::TooManyRedirects)
+        } else {
+            None
+        }
+    })
+}
+
+/// `If-None-Match` and `If-Modified-Since` headers from an earlier crawl, and the URL the
+/// page was stored under then.
+#[derive(Debug)]
+pub struct Validators {
+    pub url: Url,
+    pub headers: HeaderMap,
+}
+
+/// A page response and the URLs that redirected to it, in the order they were visited.
+#[derive(Debug)]
+pub struct Page {
+    pub response: Response,
+    pub redirects: Vec<Url>,
+}
+
+/// HTTP client shared by every worker, applying [`FetchLimits`] and sending the
+/// crawler's [`Identity`] with each request. It can also archive every exchange to WARC
+/// files, or answer requests from an archive instead of the network.
+#[derive(Debug, Clone)]
+pub struct Fetcher {
+    /// Follows no redirects, so [`Fetcher::fetch_page`] can record each hop itself.
+    client: Client,
+    /// Headers the client adds to every request, kept for the archived request records.
+    default_headers: HeaderMap,
+    limits: FetchLimits,
+    filter: AddressFilter,
+    archive: Option<Arc<WarcWriter>>,
+    replay: Option<Arc<WarcReplay>>,
+}
+
+impl Fetcher {
+    pub fn new(limits: FetchLimits, identity: &Identity) -> anyhow::Result<Self> {
+        let filter = limits.address_filter();
+
+        let mut default_headers = identity.default_headers()?;
+        default_headers.insert(USER_AGENT, HeaderValue::from_str(&identity.user_agent())?);
+
+        let client = Client::builder()
+            .default_headers(default_headers.clone())
+            .timeout(Duration::from_secs(limits.request_timeout_secs))
+            .redirect(Policy::none())
+            .dns_resolver(Arc::new(filter.clone()))
+            .build()?;
+
+        Ok(Self {
+            client,
+            default_headers,
+            limits,
+            filter,
+            archive: None,
+            replay: None,
+        })
+    }
+
+    pub fn with_archive(mut self, archive: WarcWriter) -> Self {
+        self.archive = Some(Arc::new(archive));
+        self
+    }
+
+    /// Answers every request from `replay`; nothing is fetched from the network.
+    pub fn with_replay(mut self, replay: WarcReplay) -> Self {
+        self.replay = Some(Arc::new(replay));
+        self
+    }
+
+    pub async fn get(&self, url: Url) -> anyhow::Result<Response> {
+        self.get_with_headers(url, HeaderMap::new()).await
+    }
+
+    pub async fn get_with_headers(&self, url: Url, headers: HeaderMap) -> anyhow::Result<Response> {
+        Ok(self.follow(url, headers, None, false).await?.response)
+    }
+
+    /// Fetches the page a job points at, reporting the URLs it redirected through.
+    pub async fn fetch_page(&self, url: Url, validators: &Validators) -> anyhow::Result<Page> {
+        self.follow(url, HeaderMap::new(), Some(validators), true)
+            .await
+    }
+
+    /// Follows redirects by hand so the URLs passed through on the way can be reported.
+    /// Conditional headers are only sent to the URL they were stored for, which may be
+    /// the target of a redirect rather than the requested URL.
+    async fn follow(
+        &self,
+        url: Url,
+        headers: HeaderMap,
+        validators: Option<&Validators>,
+        is_job: bool,
+    ) -> anyhow::Result<Page> {
+        let mut url = url;
+        let mut redirects = Vec::new();
+
+        loop {
+            let mut request_headers = headers.clone();
+            if let Some(validators) = validators.filter(|validators| validators.url == url) {
+                request_headers.extend(validators.headers.clone());
+            }
+
+            let res = self
+                .send(&url, &request_headers, is_job && redirects.is_empty())
+                .await?;
+
+            let location = res
+                .status()
+                .is_redirection()
+                .then(|| res.headers().get(LOCATION))
+                .flatten()
+                .and_then(|location| location.to_str().ok())
+                .and_then(|location| url.join(location).ok());
+
+            let Some(location) = location else {
+                return Ok(Page {
+                    response: res.error_for_status()?,
+                    redirects,
+                });
+            };
+
+            if redirects.len() >= self.limits.max_redirects {
+                return Err(GuardError {
+                    guard: Guard::TooManyRedirects,
+                    url: location.to_string(),
+                }
+                .into());
+            }
+
+            redirects.push(url);
+            url = location;
+        }
+    }
+
+    /// Sends a single request, refusing URLs that point straight at an internal address.
+    /// When archiving, the response is read in full and written out before it is handed
+    /// back.
+    async fn send(&self, url: &Url, headers: &HeaderMap, is_job: bool) -> anyhow::Result<Response> {
+        if let Some(replay) = &self.replay {
+            return replay.response(url);
+        }
+
+        self.filter.check_url(url)?;
+
+        let req = self
+            .client
+            .get(url.clone())
+            .headers(headers.clone())
+            .build()?;
+
+        let Some(archive) = &self.archive else {
+            return Ok(self.client.execute(req).await?);
+        };
+
+        let mut request_headers = self.default_headers.clone();
+        request_headers.extend(req.headers().clone());
+
+        let res = self.client.execute(req).await?;
+        let version = res.version();
+        let status = res.status();
+        let response_headers = res.headers().clone();
+
+        let max_bytes = self.limits.max_body_bytes.max(self.limits.max_image_bytes);
+        let body = read_guarded(res, max_bytes, Guard::BodyTooLarge).await?;
+
+        let stored = StoredResponse {
+            version,
+            status,
+            headers: response_headers,
+            body,
+        };
+        archive
+            .write_exchange(url, &request_headers, is_job, &stored)
+            .await?;
+
+        stored.into_response(url.clone())
+    }
+
+    /// Reads a page body, giving up as soon as it grows past `max_body_bytes`.
+    pub async fn read_body(&self, res: Response) -> anyhow::Result<Vec<u8>> {
+        read_guarded(res, self.limits.max_body_bytes, Guard::BodyTooLarge).await
+    }
+
+    /// Reads an HTML page, decoded with the encoding from its byte order mark,
+    /// `Content-Type` or `<meta>` declaration.
+    pub async fn read_html(&self, res: Response) -> anyhow::Result<(String, &'static Encoding)> {
+        let content_type = content_type(&res);
+        let bytes = self.read_body(res).await?;
+
+        let encoding = sniff_html_encoding(&bytes, content_type.as_deref());
+        Ok((
+            encoding.decode_with_bom_removal(&bytes).0.into_owned(),
+            encoding,
+        ))
+    }
+
+    /// Reads a non-HTML text body, decoded with the encoding from its byte order mark or
+    /// `Content-Type`.
+    pub async fn read_text(&self, res: Response) -> anyhow::Result<(String, &'static Encoding)> {
+        let content_type = content_type(&res);
+        let bytes = self.read_body(res).await?;
+
+        let encoding = sniff_text_encoding(&bytes, content_type.as_deref());
+        Ok((
+            encoding.decode_with_bom_removal(&bytes).0.into_owned(),
+            encoding,
+        ))
+    }
+
+    /// Downloads an image file, giving up as soon as it grows past `max_image_bytes`.
+    pub async fn fetch_image(&self, url: Url) -> anyhow::Result<Vec<u8>> {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT, HeaderValue::from_static(IMAGE_ACCEPT));
+
+        let res = self.get_with_headers(url, headers).await?;
+        read_guarded(res, self.limits.max_image_bytes, Guard::ImageTooLarge).await
+    }
+
+    /// Decoder limits for crawled images, so a decompression bomb fails instead of
+    /// allocating gigabytes.
+    pub fn image_limits(&self) -> image::io::Limits {
+        let mut limits = image::io::Limits::default();
+        limits.max_image_width = Some(self.limits.max_image_dimension);
+        limits.max_image_height = Some(self.limits.max_image_dimension);
+        limits.max_alloc = Some(self.limits.max_image_alloc_bytes);
+        limits
+    }
+
+    /// How long the text of a PDF may take to extract.
+    pub fn pdf_time_limit(&self) -> Duration {
+        Duration::from_secs(self.limits.max_pdf_secs)
+    }
+}
+
+fn content_type(res: &Response) -> Option<String> {
+    res.headers()
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .map(|content_type| content_type.to_owned())
+}
+
+/// Reads a response body, reporting one past `max_bytes` as stopped by `guard`.
+async fn read_guarded(res: Response, max_bytes: usize, guard: Guard) -> anyhow::Result<Vec<u8>> {
+    read_capped(res, max_bytes)
+        .await
+        .map_err(|err| match err.downcast::<TooLarge>() {
+            Ok(err) => GuardError {
+                guard,
+                url: err.url,
+            }
+            .into(),
+            Err(err) => err,
+        })
+}
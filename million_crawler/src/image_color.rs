@@ -0,0 +1,270 @@
+use image::{imageops::FilterType, DynamicImage, GenericImageView, Pixel};
+
+/// Images are shrunk to fit in this many pixels per side before their colors are read.
+const SAMPLE_SIZE: u32 = 64;
+
+/// SVGs are rendered so their longest side is this many pixels.
+const SVG_RENDER_SIZE: f32 = 256.0;
+
+/// Share of opaque pixels a color needs to count as dominant.
+const MIN_COLOR_SHARE: f32 = 0.1;
+
+const MAX_DOMINANT_COLORS: usize = 3;
+
+/// Named colors images are bucketed into; these are also the values accepted by the
+/// image search color filter.
+const COLOR_NAMES: &[&str] = &[
+    "black", "gray", "white", "red", "orange", "yellow", "green", "teal", "blue", "purple", "pink",
+    "brown",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LuminanceRange {
+    pub min: f32,
+    pub max: f32,
+    pub mean: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageColors {
+    pub luminance_range: Option<LuminanceRange>,
+    pub dominant_colors: Vec<String>,
+}
+
+pub fn image_colors(img: &DynamicImage) -> ImageColors {
+    let sample = img.resize(SAMPLE_SIZE, SAMPLE_SIZE, FilterType::Triangle);
+
+    ImageColors {
+        luminance_range: luminance_range(&sample),
+        dominant_colors: dominant_colors(&sample),
+    }
+}
+
+/// Rasterizes an SVG document so its colors can be read like any other image.
+pub fn render_svg(svg: &str) -> Option<DynamicImage> {
+    let tree = resvg::usvg::Tree::from_str(
+        svg,
+        &resvg::usvg::Options::default(),
+        &resvg::usvg::fontdb::Database::default(),
+    )
+    .ok()?;
+
+    let size = tree.size();
+    let scale = SVG_RENDER_SIZE / size.width().max(size.height());
+
+    let width = (size.width() * scale).ceil() as u32;
+    let height = (size.height() * scale).ceil() as u32;
+
+    let mut pix_map = resvg::tiny_skia::Pixmap::new(width, height)?;
+
+    resvg::render(
+        &tree,
+        resvg::tiny_skia::Transform::from_scale(scale, scale),
+        &mut pix_map.as_mut(),
+    );
+
+    // tiny-skia stores premultiplied alpha.
+    let data = pix_map
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let pixel = pixel.demultiply();
+            [pixel.red(), pixel.green(), pixel.blue(), pixel.alpha()]
+        })
+        .collect();
+
+    image::RgbaImage::from_vec(width, height, data).map(DynamicImage::from)
+}
+
+/// Relative luminance range and mean over the image's opaque pixels.
+fn luminance_range(img: &DynamicImage) -> Option<LuminanceRange> {
+    let mut min_luminance = f32::INFINITY;
+    let mut max_luminance = -f32::INFINITY;
+    let mut total = 0.0;
+    let mut count = 0;
+
+    for (_, _, p) in img.pixels() {
+        if p[3] > 127 {
+            let luminance = get_luminance(
+                p[0] as f32 / 255f32,
+                p[1] as f32 / 255f32,
+                p[2] as f32 / 255f32,
+            );
+            min_luminance = f32::min(min_luminance, luminance);
+            max_luminance = f32::max(max_luminance, luminance);
+            total += luminance;
+            count += 1;
+        }
+    }
+
+    (count > 0).then(|| LuminanceRange {
+        min: min_luminance,
+        max: max_luminance,
+        mean: total / count as f32,
+    })
+}
+
+/// Up to [`MAX_DOMINANT_COLORS`] names from [`COLOR_NAMES`], most common first.
+fn dominant_colors(img: &DynamicImage) -> Vec<String> {
+    let mut counts = [0usize; COLOR_NAMES.len()];
+    let mut total = 0;
+
+    for (_, _, p) in img.pixels() {
+        if p[3] > 127 {
+            let rgb = p.to_rgb();
+            counts[color_bucket(rgb[0], rgb[1], rgb[2])] += 1;
+            total += 1;
+        }
+    }
+
+    if total == 0 {
+        return vec![];
+    }
+
+    let mut colors = counts
+        .into_iter()
+        .enumerate()
+        .filter(|(_, count)| *count as f32 / total as f32 >= MIN_COLOR_SHARE)
+        .collect::<Vec<_>>();
+    colors.sort_by(|(_, a), (_, b)| b.cmp(a));
+
+    colors
+        .into_iter()
+        .take(MAX_DOMINANT_COLORS)
+        .map(|(i, _)| COLOR_NAMES[i].to_owned())
+        .collect()
+}
+
+/// Index into [`COLOR_NAMES`] for an sRGB pixel, bucketed by hue, saturation and lightness.
+fn color_bucket(r: u8, g: u8, b: u8) -> usize {
+    let (r, g, b) = (r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0);
+
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let lightness = (max + min) / 2.0;
+    let chroma = max - min;
+
+    let bucket = |name| COLOR_NAMES.iter().position(|color| *color == name).unwrap();
+
+    if lightness < 0.12 {
+        return bucket("black");
+    }
+    if lightness > 0.92 {
+        return bucket("white");
+    }
+    if chroma < 0.12 {
+        return bucket("gray");
+    }
+
+    let hue = if max == r {
+        60.0 * ((g - b) / chroma).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / chroma + 2.0)
+    } else {
+        60.0 * ((r - g) / chroma + 4.0)
+    };
+
+    match hue {
+        hue if !(15.0..345.0).contains(&hue) => {
+            if lightness > 0.7 {
+                bucket("pink")
+            } else {
+                bucket("red")
+            }
+        }
+        hue if hue < 45.0 => {
+            if lightness < 0.4 {
+                bucket("brown")
+            } else {
+                bucket("orange")
+            }
+        }
+        hue if hue < 70.0 => bucket("yellow"),
+        hue if hue < 160.0 => bucket("green"),
+        hue if hue < 195.0 => bucket("teal"),
+        hue if hue < 260.0 => bucket("blue"),
+        hue if hue < 300.0 => bucket("purple"),
+        _ => bucket("pink"),
+    }
+}
+
+fn get_luminance(r: f32, g: f32, b: f32) -> f32 {
+    let r = srgb_to_linear(r);
+    let g = srgb_to_linear(g);
+    let b = srgb_to_linear(b);
+
+    0.2126 * r + 0.7152 * g + 0.0722 * b
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        f32::powf((c + 0.055) / 1.055, 2.4)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgba, RgbaImage};
+
+    use super::*;
+
+    fn solid(color: [u8; 4]) -> DynamicImage {
+        DynamicImage::from(RgbaImage::from_pixel(100, 80, Rgba(color)))
+    }
+
+    #[test]
+    fn reads_a_solid_color() {
+        let colors = image_colors(&solid([220, 30, 30, 255]));
+
+        assert_eq!(colors.dominant_colors, ["red"]);
+        let range = colors.luminance_range.unwrap();
+        assert_eq!(range.min, range.max);
+        assert!(
+            (range.mean - get_luminance(220.0 / 255.0, 30.0 / 255.0, 30.0 / 255.0)).abs() < 1e-4
+        );
+
+        let colors = image_colors(&solid([255, 255, 255, 255]));
+        assert_eq!(colors.dominant_colors, ["white"]);
+        assert_eq!(colors.luminance_range.map(|range| range.max), Some(1.0));
+    }
+
+    #[test]
+    fn ignores_transparent_pixels() {
+        let colors = image_colors(&solid([0, 0, 255, 0]));
+        assert_eq!(
+            colors,
+            ImageColors {
+                luminance_range: None,
+                dominant_colors: vec![],
+            }
+        );
+
+        // Only the opaque half counts, so the transparent blue does not show up.
+        let mut img = RgbaImage::from_pixel(64, 64, Rgba([0, 0, 255, 0]));
+        for (x, _, pixel) in img.enumerate_pixels_mut() {
+            if x < 32 {
+                *pixel = Rgba([30, 160, 40, 255]);
+            }
+        }
+        assert_eq!(
+            image_colors(&DynamicImage::from(img)).dominant_colors,
+            ["green"]
+        );
+    }
+
+    #[test]
+    fn renders_svgs_and_skips_empty_ones() {
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="10">
+            <rect width="20" height="10" fill="#ffd700"/>
+        </svg>"##;
+        let img = render_svg(svg).unwrap();
+        assert_eq!(img.dimensions(), (256, 128));
+        assert_eq!(image_colors(&img).dominant_colors, ["yellow"]);
+
+        let empty = r#"<svg xmlns="http://www.w3.org/2000/svg" width="0" height="0"/>"#;
+        assert!(render_svg(empty).is_none());
+        assert!(render_svg("not an svg").is_none());
+    }
+}
@@ -0,0 +1,203 @@
+use percent_encoding::percent_decode_str;
+use scraper::ElementRef;
+use url::Url;
+
+/// Longest text kept from each piece of surrounding context.
+const MAX_CONTEXT_CHARS: usize = 300;
+
+/// Images whose width or height is below this many pixels are treated as tracking pixels
+/// or spacers and skipped.
+pub const MIN_IMAGE_SIDE: u32 = 16;
+
+/// An `<img>` found on a page together with the text around it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageElement {
+    /// The largest of the image's variants.
+    pub url: Url,
+    /// Every resolution offered through `src`, `srcset`, `<picture>` sources and
+    /// lazy-loading attributes.
+    pub variants: Vec<ImageVariant>,
+    pub alt_text: Option<String>,
+    pub context: ImageContext,
+    /// Size declared by the `width` and `height` attributes.
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageVariant {
+    pub url: Url,
+    pub width: Option<u32>,
+    pub density: Option<f32>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ImageContext {
+    /// `<figcaption>` of the enclosing `<figure>`.
+    pub caption: Option<String>,
+    /// The image's `title` attribute.
+    pub title: Option<String>,
+    /// The closest heading before the image.
+    pub heading: Option<String>,
+    /// The paragraph containing the image, or the one next to it.
+    pub paragraph: Option<String>,
+    /// Words from the image's file name, e.g. `red-panda_2.jpg` gives `red` and `panda`.
+    pub filename_tokens: Vec<String>,
+}
+
+impl ImageElement {
+    /// Whether anything describes this particular image. The nearest heading and the file
+    /// name alone do not count: the heading describes the page section, and names like
+    /// `IMG_2041.jpg` or `hero-banner.png` say little about what is shown.
+    pub fn has_usable_text(&self) -> bool {
+        self.alt_text.is_some()
+            || self.context.caption.is_some()
+            || self.context.title.is_some()
+            || self.context.paragraph.is_some()
+    }
+
+    /// Whether the declared size already marks the image as too small to index.
+    pub fn is_declared_tiny(&self) -> bool {
+        is_tiny(self.width, self.height)
+    }
+}
+
+pub fn is_tiny(width: Option<u32>, height: Option<u32>) -> bool {
+    width.is_some_and(|width| width < MIN_IMAGE_SIDE)
+        || height.is_some_and(|height| height < MIN_IMAGE_SIDE)
+}
+
+/// Text of an element with whitespace collapsed, cut to [`MAX_CONTEXT_CHARS`].
+pub fn element_text(element: ElementRef) -> Option<String> {
+    clean_text(&element.text().collect::<String>())
+}
+
+pub fn clean_text(text: &str) -> Option<String> {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    let text = match text.char_indices().nth(MAX_CONTEXT_CHARS) {
+        Some((end, _)) => text[..end].to_owned(),
+        None => text,
+    };
+
+    (!text.is_empty()).then_some(text)
+}
+
+/// Lowercase words from the last path segment, leaving out the extension, numbers and
+/// hash-like runs of letters and digits.
+pub fn filename_tokens(url: &Url) -> Vec<String> {
+    let Some(name) = url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+    else {
+        return vec![];
+    };
+
+    let name = percent_decode_str(name).decode_utf8_lossy();
+    let stem = name.rsplit_once('.').map_or(&*name, |(stem, _)| stem);
+
+    stem.split(|c: char| !c.is_alphanumeric())
+        .filter(|token| token.chars().count() >= 2)
+        .filter(|token| token.chars().all(char::is_alphabetic))
+        .map(|token| token.to_lowercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use scraper::Html;
+
+    use crate::selector_set::SelectorSet;
+
+    use super::*;
+
+    fn images(html: &str) -> Vec<ImageElement> {
+        SelectorSet::new().select_images(
+            &Html::parse_document(html),
+            &Url::parse("https://example.com/blog/post.html").unwrap(),
+        )
+    }
+
+    #[test]
+    fn captures_surrounding_text() {
+        let images = images(
+            r#"<h1>Zoo</h1>
+            <section>
+              <h2>Red pandas</h2>
+              <figure>
+                <img src="/img/red-panda_2.jpg" title=" Sleeping ">
+                <figcaption> A red   panda asleep </figcaption>
+              </figure>
+              <p>They spend most of the day in trees.</p>
+              <p>Feeding time is at noon <img src="feeding.png" width="400" height="8"> daily.</p>
+            </section>"#,
+        );
+
+        assert_eq!(images.len(), 2);
+
+        let panda = &images[0].context;
+        assert_eq!(panda.caption.as_deref(), Some("A red panda asleep"));
+        assert_eq!(panda.title.as_deref(), Some("Sleeping"));
+        assert_eq!(panda.heading.as_deref(), Some("Red pandas"));
+        assert_eq!(
+            panda.paragraph.as_deref(),
+            Some("They spend most of the day in trees.")
+        );
+        assert_eq!(panda.filename_tokens, ["red", "panda"]);
+
+        let feeding = &images[1];
+        assert_eq!(feeding.context.caption, None);
+        assert_eq!(
+            feeding.context.paragraph.as_deref(),
+            Some("Feeding time is at noon daily.")
+        );
+        assert!(feeding.is_declared_tiny());
+    }
+
+    #[test]
+    fn file_names_and_headings_alone_are_not_usable_text() {
+        let images = images(
+            r#"<h2>Gallery</h2>
+            <div><img src="/img/sunset-over-the-bay.jpg"></div>
+            <div><img src="/img/sunset.jpg" alt="Sunset over the bay"></div>"#,
+        );
+
+        assert_eq!(images[0].context.heading.as_deref(), Some("Gallery"));
+        assert_eq!(
+            images[0].context.filename_tokens,
+            ["sunset", "over", "the", "bay"]
+        );
+        assert!(!images[0].has_usable_text());
+        assert!(images[1].has_usable_text());
+    }
+
+    #[test]
+    fn tokenizes_file_names() {
+        let tokens = |url: &str| filename_tokens(&Url::parse(url).unwrap());
+
+        assert_eq!(
+            tokens("https://example.com/a/Red-Panda_2.JPG"),
+            ["red", "panda"]
+        );
+        assert_eq!(
+            tokens("https://example.com/caf%C3%A9%20au%20lait.webp"),
+            ["café", "au", "lait"]
+        );
+        assert_eq!(tokens("https://example.com/IMG_2041.jpg"), ["img"]);
+        assert!(tokens("https://example.com/3f9a1c2b7e.png").is_empty());
+        assert!(tokens("https://example.com/").is_empty());
+    }
+
+    #[test]
+    fn cleans_and_shortens_text() {
+        assert_eq!(clean_text("  two\n  words ").as_deref(), Some("two words"));
+        assert_eq!(clean_text(" \n "), None);
+        assert_eq!(
+            clean_text(&"é".repeat(MAX_CONTEXT_CHARS + 10))
+                .unwrap()
+                .chars()
+                .count(),
+            MAX_CONTEXT_CHARS
+        );
+    }
+}
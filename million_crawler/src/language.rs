@@ -0,0 +1,96 @@
+use isolang::Language;
+use whatlang::Info;
+
+/// Picks the language of a page, preferring confident text detection over the
+/// declared `<html lang>` and `Content-Language` values, which are often left
+/// at a template default.
+pub fn detect_language(
+    html_lang: Option<&str>,
+    content_language: Option<&str>,
+    text: &str,
+) -> Option<String> {
+    let detected = whatlang::detect(text);
+
+    detected
+        .as_ref()
+        .filter(|info| info.is_reliable())
+        .and_then(info_to_code)
+        .or_else(|| html_lang.and_then(normalize_language_tag))
+        .or_else(|| {
+            content_language
+                .and_then(|header| header.split(',').next())
+                .and_then(normalize_language_tag)
+        })
+        .or_else(|| detected.as_ref().and_then(info_to_code))
+}
+
+/// Reduces a BCP 47 tag such as `en-US` to its lowercase primary subtag.
+pub fn normalize_language_tag(tag: &str) -> Option<String> {
+    let primary = tag.trim().split(['-', '_']).next()?.to_ascii_lowercase();
+
+    if primary.len() == 2 && Language::from_639_1(&primary).is_some() {
+        Some(primary)
+    } else {
+        Language::from_639_3(&primary)
+            .and_then(|lang| lang.to_639_1())
+            .map(|code| code.to_owned())
+    }
+}
+
+fn info_to_code(info: &Info) -> Option<String> {
+    Language::from_639_3(info.lang().code())
+        .and_then(|lang| lang.to_639_1())
+        .map(|code| code.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENGLISH: &str = "The crawler reads every page it is given, follows the links it \
+        finds there and stores the text so that people can search through it later on.";
+    const GERMAN: &str = "Der Crawler liest jede Seite, die er bekommt, folgt den Links, die \
+        er dort findet, und speichert den Text, damit man ihn später durchsuchen kann.";
+
+    #[test]
+    fn normalizes_language_tags() {
+        assert_eq!(normalize_language_tag("en").as_deref(), Some("en"));
+        assert_eq!(normalize_language_tag("en-US").as_deref(), Some("en"));
+        assert_eq!(normalize_language_tag(" EN_us ").as_deref(), Some("en"));
+        assert_eq!(normalize_language_tag("deu").as_deref(), Some("de"));
+        assert_eq!(normalize_language_tag(""), None);
+        assert_eq!(normalize_language_tag("xx"), None);
+        assert_eq!(normalize_language_tag("*"), None);
+    }
+
+    #[test]
+    fn prefers_confident_detection_over_declared_language() {
+        assert_eq!(
+            detect_language(Some("en"), Some("en-US"), GERMAN).as_deref(),
+            Some("de")
+        );
+        assert_eq!(detect_language(None, None, ENGLISH).as_deref(), Some("en"));
+    }
+
+    #[test]
+    fn falls_back_to_declared_language_for_short_text() {
+        assert_eq!(
+            detect_language(Some("fr-FR"), Some("de"), "Menu").as_deref(),
+            Some("fr")
+        );
+        assert_eq!(
+            detect_language(None, Some("de-DE, en;q=0.5"), "Menu").as_deref(),
+            Some("de")
+        );
+        assert_eq!(
+            detect_language(Some("not a tag"), Some("nl"), "").as_deref(),
+            Some("nl")
+        );
+    }
+
+    #[test]
+    fn gives_up_without_text_or_declaration() {
+        assert_eq!(detect_language(None, None, ""), None);
+        assert_eq!(detect_language(Some(""), Some(""), "  "), None);
+    }
+}
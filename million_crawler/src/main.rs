@@ -1,25 +1,67 @@
-use std::{io::Cursor, time::Duration};
+use std::{
+    io::Cursor,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use exponential_backoff::Backoff;
 use futures::future::join_all;
+use image::ImageError;
 use lazy_static::lazy_static;
+use million_common::image_hash;
 use proto::{
     crawler::{
         crawler_client::CrawlerClient,
         return_job_request::{self},
-        GetJobRequest, GetJobResponse, ReturnJobRequest,
+        DocumentType, GetJobRequest, GetJobResponse, Guard, ReturnJobRequest,
     },
     tonic::{codec::CompressionEncoding, transport::Channel, Code, Status},
 };
+use reqwest::{
+    header::{
+        HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH,
+        LAST_MODIFIED,
+    },
+    StatusCode, Version,
+};
 use serde::Deserialize;
+use sha2::{Digest, Sha256};
 use tokio::task::spawn_blocking;
 use tracing::info;
 use url::Url;
 
-use crate::selector_set::SelectorSet;
+use crate::{
+    content::extract_main_content,
+    feed::{parse_feed, FeedDocument},
+    fetch::{tripped_guard, FetchLimits, Fetcher, GuardError, Identity, Page, Validators},
+    image_color::{image_colors, render_svg},
+    image_context::is_tiny,
+    language::detect_language,
+    media::{probe_media, MediaKind, MAX_PROBED_MEDIA},
+    pdf::extract_pdf,
+    render::{RenderOptions, Renderer},
+    selector_set::SelectorSet,
+    text_formats::{extract_markdown, extract_plain_text, extract_xml},
+    thumbnail::webp_thumbnail,
+    warc::{StoredResponse, WarcOptions, WarcReplay, WarcWriter},
+};
 
+mod content;
+mod encoding;
+mod feed;
+mod fetch;
+mod image_color;
+mod image_context;
+mod language;
+mod media;
+mod pdf;
+mod render;
 mod selector_set;
+mod srcset;
+mod text_formats;
+mod thumbnail;
+mod warc;
 
 lazy_static! {
     static ref SELECTOR: SelectorSet = SelectorSet::new();
@@ -30,30 +72,82 @@ lazy_static! {
 struct Args {
     #[arg(short, long, env, default_value_t = String::from("http://localhost:8080"))]
     endpoint: String,
+
+    #[command(flatten)]
+    limits: FetchLimits,
+
+    #[command(flatten)]
+    identity: Identity,
+
+    #[command(flatten)]
+    render: RenderOptions,
+
+    #[command(flatten)]
+    warc: WarcOptions,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Clone)]
+enum Command {
+    /// Re-runs extraction over pages stored in WARC files instead of fetching them, and
+    /// prints the results.
+    Replay {
+        /// A WARC file, or a directory of them.
+        path: PathBuf,
+
+        /// Pages to replay. Defaults to every page the archive recorded as a job.
+        urls: Vec<Url>,
+    },
+    /// Crawls a single URL, or extracts a local file, and prints what the backend would
+    /// receive as JSON. Image thumbnails are left out.
+    Inspect {
+        /// An `http(s)` URL, or the path of a saved HTML, PDF, text or feed file.
+        target: String,
+    },
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    tracing_subscriber::fmt().init();
+    // Keep stdout for the JSON printed by `replay` and `inspect`.
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .init();
 
     let args = Args::parse();
 
-    tokio::select! {
-        _ = run_many(args, 1) => {}
-        _ = tokio::time::sleep(Duration::from_secs(60 * 30)) => {}
+    match args.command.clone() {
+        Some(Command::Replay { path, urls }) => replay(&args, &path, urls).await?,
+        Some(Command::Inspect { target }) => inspect(&args, &target).await?,
+        None => {
+            tokio::select! {
+                _ = run_many(args, 1) => {}
+                _ = tokio::time::sleep(Duration::from_secs(60 * 30)) => {}
+            }
+        }
     }
 
     Ok(())
 }
 
 async fn run_many(args: Args, parallel_tasks: usize) -> anyhow::Result<()> {
+    // One set of pooled connections for every worker.
+    let fetcher = network_fetcher(&args).await?;
+    let renderer = Renderer::new(
+        args.render.clone(),
+        args.identity.user_agent(),
+        args.limits.address_filter(),
+    )
+    .await;
+
     let mut tasks = Vec::new();
 
     for _ in 0..parallel_tasks {
-        tasks.push(run(args.clone()));
+        tasks.push(run(args.clone(), fetcher.clone(), renderer.clone()));
     }
 
-    join_all(tasks.into_iter())
+    join_all(tasks)
         .await
         .into_iter()
         .collect::<Result<Vec<_>, _>>()?;
@@ -61,7 +155,139 @@ async fn run_many(args: Args, parallel_tasks: usize) -> anyhow::Result<()> {
     Ok(())
 }
 
-async fn run(args: Args) -> anyhow::Result<()> {
+async fn replay(args: &Args, path: &Path, urls: Vec<Url>) -> anyhow::Result<()> {
+    let archive = WarcReplay::open(path)?;
+    let urls = if urls.is_empty() {
+        archive.jobs().to_vec()
+    } else {
+        urls
+    };
+
+    let fetcher = Fetcher::new(args.limits.clone(), &args.identity)?.with_replay(archive);
+
+    // A browser would go to the network, so replays only see what was archived.
+    let mut render = args.render.clone();
+    render.chrome_url = None;
+    render.chrome_executable = None;
+    let renderer = Renderer::new(
+        render,
+        args.identity.user_agent(),
+        args.limits.address_filter(),
+    )
+    .await;
+
+    for url in urls {
+        let job = GetJobResponse {
+            id: 0,
+            url: url.to_string(),
+            etag: None,
+            last_modified: None,
+            validated_url: None,
+        };
+
+        match do_job(&fetcher, &renderer, &job).await {
+            Ok(result) => println!("{}", serde_json::to_string_pretty(&result)?),
+            Err(err) => tracing::error!("replaying {} failed: {}", url, err),
+        }
+    }
+
+    Ok(())
+}
+
+async fn inspect(args: &Args, target: &str) -> anyhow::Result<()> {
+    let (fetcher, renderer, url) = match Url::parse(target) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => {
+            let renderer = Renderer::new(
+                args.render.clone(),
+                args.identity.user_agent(),
+                args.limits.address_filter(),
+            )
+            .await;
+            (network_fetcher(args).await?, renderer, url)
+        }
+        _ => {
+            let (url, response) = local_file_response(Path::new(target))?;
+
+            let mut archive = WarcReplay::default();
+            archive.insert(&url, response);
+
+            let mut render = args.render.clone();
+            render.chrome_url = None;
+            render.chrome_executable = None;
+            let renderer = Renderer::new(
+                render,
+                args.identity.user_agent(),
+                args.limits.address_filter(),
+            )
+            .await;
+
+            let fetcher = Fetcher::new(args.limits.clone(), &args.identity)?.with_replay(archive);
+            (fetcher, renderer, url)
+        }
+    };
+
+    let job = GetJobResponse {
+        id: 0,
+        url: url.to_string(),
+        etag: None,
+        last_modified: None,
+        validated_url: None,
+    };
+
+    // Without validators the server cannot answer "not modified", so this is always a page.
+    let return_job_request::Result::Ok(result) = do_job(&fetcher, &renderer, &job).await? else {
+        anyhow::bail!("{} returned no page", url);
+    };
+
+    println!("{}", serde_json::to_string_pretty(&result)?);
+
+    Ok(())
+}
+
+/// Serves a saved file as if it had been fetched, typed by its extension.
+fn local_file_response(path: &Path) -> anyhow::Result<(Url, StoredResponse)> {
+    let path = path.canonicalize()?;
+    let url = Url::from_file_path(&path)
+        .map_err(|_| anyhow::anyhow!("{} is not a valid file path", path.display()))?;
+
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let content_type = match extension.as_str() {
+        "pdf" => "application/pdf",
+        "txt" => "text/plain",
+        "md" | "markdown" => "text/markdown",
+        "xml" => "application/xml",
+        "rss" => "application/rss+xml",
+        "atom" => "application/atom+xml",
+        _ => "text/html",
+    };
+
+    let mut headers = HeaderMap::new();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+
+    let response = StoredResponse {
+        version: Version::HTTP_11,
+        status: StatusCode::OK,
+        headers,
+        body: std::fs::read(&path)?,
+    };
+
+    Ok((url, response))
+}
+
+/// A fetcher for the live web, archiving to WARC files when `--warc-dir` is set.
+async fn network_fetcher(args: &Args) -> anyhow::Result<Fetcher> {
+    let mut fetcher = Fetcher::new(args.limits.clone(), &args.identity)?;
+    if let Some(archive) = WarcWriter::new(&args.warc).await? {
+        fetcher = fetcher.with_archive(archive);
+    }
+    Ok(fetcher)
+}
+
+async fn run(args: Args, fetcher: Fetcher, renderer: Renderer) -> anyhow::Result<()> {
     let mut client = CrawlerClient::connect(args.endpoint)
         .await?
         .send_compressed(CompressionEncoding::Zstd)
@@ -73,12 +299,12 @@ async fn run(args: Args) -> anyhow::Result<()> {
 
         let start_time = std::time::Instant::now();
 
-        match do_job(&job).await {
+        match do_job(&fetcher, &renderer, &job).await {
             Ok(res) => {
                 let ret = ReturnJobRequest {
                     id: job.id,
                     url: job.url.clone(),
-                    result: Some(return_job_request::Result::Ok(res)),
+                    result: Some(res),
                 };
 
                 let _ = client.return_job(ret).await;
@@ -87,7 +313,9 @@ async fn run(args: Args) -> anyhow::Result<()> {
                 let ret = ReturnJobRequest {
                     id: job.id,
                     url: job.url.clone(),
-                    result: Some(return_job_request::Result::Err(return_job_request::Err {})),
+                    result: Some(return_job_request::Result::Err(return_job_request::Err {
+                        guard: tripped_guard(&err).unwrap_or(Guard::None) as i32,
+                    })),
                 };
 
                 let _ = client.return_job(ret).await;
@@ -107,37 +335,109 @@ async fn run(args: Args) -> anyhow::Result<()> {
     }
 }
 
-async fn do_job(job: &GetJobResponse) -> anyhow::Result<return_job_request::Ok> {
-    let res = reqwest::get(&job.url).await?.error_for_status()?;
+async fn do_job(
+    fetcher: &Fetcher,
+    renderer: &Renderer,
+    job: &GetJobResponse,
+) -> anyhow::Result<return_job_request::Result> {
+    let mut conditional = HeaderMap::new();
+    if let Some(etag) = &job.etag {
+        conditional.insert(IF_NONE_MATCH, HeaderValue::from_str(etag)?);
+    }
+    if let Some(last_modified) = &job.last_modified {
+        conditional.insert(IF_MODIFIED_SINCE, HeaderValue::from_str(last_modified)?);
+    }
+    let validators = Validators {
+        url: job.validated_url.as_deref().unwrap_or(&job.url).parse()?,
+        headers: conditional,
+    };
+
+    let page = fetcher.fetch_page(job.url.parse()?, &validators).await?;
+
+    if page.response.status() == StatusCode::NOT_MODIFIED {
+        return Ok(return_job_request::Result::NotModified(
+            return_job_request::NotModified {},
+        ));
+    }
+
+    crawl_page(fetcher, renderer, page)
+        .await
+        .map(return_job_request::Result::Ok)
+}
+
+async fn crawl_page(
+    fetcher: &Fetcher,
+    renderer: &Renderer,
+    page: Page,
+) -> anyhow::Result<return_job_request::Ok> {
+    let res = page.response;
     let status = res.status();
 
+    // Links are resolved against, and the page is indexed under, the URL it was served from.
+    let page_url = res.url().clone();
+    let final_url = page_url.to_string();
+    let redirects: Vec<String> = page.redirects.iter().map(|url| url.to_string()).collect();
+
     let headers = res.headers();
 
+    let etag = header_string(headers, ETAG);
+    let last_modified = header_string(headers, LAST_MODIFIED);
+
     let mime_type = headers
         .get("Content-Type")
         .and_then(|mt| mt.to_str().ok().map(|mt| mt.to_owned()))
         .unwrap_or_default();
 
+    let mime_essence = mime_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+
+    let text_format = match mime_essence.as_str() {
+        "text/plain" => Some(DocumentType::PlainText),
+        "text/markdown" | "text/x-markdown" => Some(DocumentType::Markdown),
+        "application/xml" | "text/xml" => Some(DocumentType::Xml),
+        _ => None,
+    };
+
+    let is_feed = matches!(
+        mime_essence.as_str(),
+        "application/rss+xml" | "application/atom+xml" | "application/feed+json"
+    );
+
+    let content_language = headers
+        .get("Content-Language")
+        .and_then(|cl| cl.to_str().ok().map(|cl| cl.to_owned()));
+
     Ok(if mime_type.is_empty() || mime_type.contains("html") {
-        let text = res.text().await?;
+        let (mut text, encoding) = fetcher.read_html(res).await?;
 
-        let job_url: Url = job.url.parse()?;
+        // Script-built pages arrive as an empty shell; let a browser fill them in.
+        if let Some(rendered) = renderer.render_if_needed(&page_url, &text).await {
+            text = rendered;
+        }
+
+        let job_url = page_url.clone();
 
         let job_url_other = job_url.clone();
 
-        let (html, urls, manifest_url, keywords) = spawn_blocking(move || {
+        let (html, urls, anchors, manifest_url, keywords, content) = spawn_blocking(move || {
             let html = scraper::Html::parse_document(&text);
             let urls = SELECTOR.select_urls(&html, &job_url_other);
+            let anchors = SELECTOR.select_anchors(&html, &job_url_other);
             let manifest_url = SELECTOR.select_manifest_url(&html, &job_url_other);
             let keywords = SELECTOR.select_keywords(&html);
-            (html, urls, manifest_url, keywords)
+            let content = extract_main_content(&html);
+            (html, urls, anchors, manifest_url, keywords, content)
         })
         .await?;
 
         let manifest = if let Some(manifest_url) = manifest_url {
-            let manifest_res = reqwest::get(manifest_url).await?.error_for_status()?;
+            let manifest_res = fetcher.get(manifest_url).await?;
 
-            let text = manifest_res.text().await?;
+            let (text, _) = fetcher.read_text(manifest_res).await?;
 
             let manifest = serde_json::from_str::<Manifest>(&text)?;
 
@@ -151,54 +451,199 @@ async fn do_job(job: &GetJobResponse) -> anyhow::Result<return_job_request::Ok>
             None
         };
 
-        let images = join_all(SELECTOR.select_images(&html, &job_url).into_iter().map(
-            |(image_url, image_alt_text)| async move {
-                let img_res = reqwest::get(image_url.clone()).await?;
-                let img_bytes = img_res.bytes().await?;
-                // let svg_text = String::from_utf8(img_bytes.to_vec()).ok();
-
-                let img = spawn_blocking(move || {
-                    image::io::Reader::new(Cursor::new(&img_bytes))
-                        .with_guessed_format()
-                        .ok()
-                        .and_then(|img| img.decode().ok())
-                })
-                .await?;
-
-                let size = img
-                    .as_ref()
-                    .map(|img| return_job_request::ok::body::image::Size {
-                        width: img.width() as i32,
-                        height: img.height() as i32,
-                    });
-
-                // let luminance_range = spawn_blocking(move || {
-                //     img.as_ref()
-                //         .and_then(get_luminance_range_image)
-                //         .or_else(|| {
-                //             svg_text
-                //                 .as_deref()
-                //                 .and_then(get_luminance_range_svg)
-                //         })
-                // })
-                // .await?;
-
-                anyhow::Result::Ok(return_job_request::ok::body::Image {
-                    image_url: image_url.to_string(),
-                    size,
-                    alt_text: image_alt_text,
-                    // luminance_range,
+        let image_elements = SELECTOR
+            .select_images(&html, &job_url)
+            .into_iter()
+            .filter(|image| image.has_usable_text() && !image.is_declared_tiny());
+
+        let images = join_all(image_elements.map(|image| async move {
+            // One bad image should not lose the whole page.
+            let img_bytes = match fetcher.fetch_image(image.url.clone()).await {
+                Ok(img_bytes) => img_bytes,
+                Err(err) => {
+                    tracing::warn!("Skipping image {}: {}", image.url, err);
+                    return Ok(None);
+                }
+            };
+            let content_hash = Sha256::digest(&img_bytes).to_vec();
+
+            let limits = fetcher.image_limits();
+            let decoded = spawn_blocking(move || {
+                let img = image::io::Reader::new(Cursor::new(&img_bytes))
+                    .with_guessed_format()
+                    .map_err(ImageError::from)
+                    .and_then(|mut reader| {
+                        reader.limits(limits);
+                        reader.decode()
+                    })
+                    .ok();
+
+                // SVGs are not decoded by `image`, so render them to read their colors
+                // and make a thumbnail.
+                let svg = match &img {
+                    Some(_) => None,
+                    None => std::str::from_utf8(&img_bytes).ok().and_then(render_svg),
+                };
+                let rendered = img.as_ref().or(svg.as_ref());
+
+                let colors = rendered.map(image_colors);
+                let thumbnail = rendered.and_then(webp_thumbnail);
+
+                Some((img, colors, thumbnail))
+            })
+            .await?;
+
+            let Some((img, colors, thumbnail)) = decoded else {
+                tracing::warn!(
+                    "Skipping image {}: {}",
+                    image.url,
+                    GuardError {
+                        guard: Guard::ImageTooLarge,
+                        url: image.url.to_string(),
+                    }
+                );
+                return Ok(None);
+            };
+
+            if is_tiny(
+                img.as_ref().map(|img| img.width()),
+                img.as_ref().map(|img| img.height()),
+            ) {
+                return Ok(None);
+            }
+
+            let perceptual_hash = img.as_ref().map(image_hash::dhash);
+
+            let size = img
+                .as_ref()
+                .map(|img| return_job_request::ok::body::image::Size {
+                    width: img.width() as i32,
+                    height: img.height() as i32,
+                });
+
+            let (luminance_range, dominant_colors) = colors
+                .map(|colors| (colors.luminance_range, colors.dominant_colors))
+                .unwrap_or_default();
+
+            let context = image.context;
+            let variants = image
+                .variants
+                .into_iter()
+                .map(|variant| return_job_request::ok::body::image::Variant {
+                    url: variant.url.to_string(),
+                    width: variant.width,
+                    density: variant.density,
                 })
-            },
-        ))
+                .collect();
+
+            anyhow::Result::Ok(Some(return_job_request::ok::body::Image {
+                image_url: image.url.to_string(),
+                size,
+                alt_text: image.alt_text,
+                perceptual_hash,
+                luminance_range: luminance_range.map(|range| {
+                    return_job_request::ok::body::image::LuminanceRange {
+                        min: range.min,
+                        max: range.max,
+                        mean: range.mean,
+                    }
+                }),
+                dominant_colors,
+                thumbnail,
+                content_hash,
+                context: Some(return_job_request::ok::body::image::Context {
+                    caption: context.caption,
+                    title: context.title,
+                    heading: context.heading,
+                    paragraph: context.paragraph,
+                    filename_tokens: context.filename_tokens,
+                }),
+                variants,
+            }))
+        }))
         .await
         .into_iter()
+        .filter_map(Result::transpose)
         .collect::<anyhow::Result<Vec<_>>>()?;
 
+        let mut videos = Vec::new();
+        let mut audios = Vec::new();
+
+        for (i, media) in SELECTOR
+            .select_media(&html, &job_url)
+            .into_iter()
+            .enumerate()
+        {
+            let info = if i < MAX_PROBED_MEDIA {
+                probe_media(fetcher, &media).await.unwrap_or_default()
+            } else {
+                Default::default()
+            };
+
+            let length_millis = info.length_millis;
+
+            match media.kind {
+                MediaKind::Video => videos.push(return_job_request::ok::body::Video {
+                    url: media.url.to_string(),
+                    title: media.title,
+                    mime_type: media.mime_type,
+                    size: info
+                        .width
+                        .zip(info.height)
+                        .or(media.width.zip(media.height))
+                        .map(
+                            |(width, height)| return_job_request::ok::body::image::Size {
+                                width: width as i32,
+                                height: height as i32,
+                            },
+                        ),
+                    length_millis,
+                    poster_url: media.poster_url.map(|url| url.to_string()),
+                }),
+                MediaKind::Audio => audios.push(return_job_request::ok::body::Audio {
+                    url: media.url.to_string(),
+                    title: media.title,
+                    mime_type: media.mime_type,
+                    length_millis,
+                }),
+            }
+        }
+
+        let (text_fields, sections) = match content {
+            Some(content) => (content.text_fields, content.sections),
+            None => (
+                SELECTOR.select_text_fields(&html),
+                SELECTOR.select_sections(&html),
+            ),
+        };
+
+        let language = detect_language(
+            SELECTOR.select_language(&html).as_deref(),
+            content_language.as_deref(),
+            &text_fields.join("\n"),
+        );
+
         return_job_request::Ok {
             status: status.as_u16() as i32,
             mime_type,
+            etag,
+            last_modified,
+            final_url,
+            redirects,
+            feed: None,
             linked_urls: urls.into_iter().map(|url| url.to_string()).collect(),
+            feed_urls: SELECTOR
+                .select_feed_urls(&html, &job_url)
+                .into_iter()
+                .map(|url| url.to_string())
+                .collect(),
+            anchors: anchors
+                .into_iter()
+                .map(|(url, text)| return_job_request::ok::Anchor {
+                    url: url.to_string(),
+                    text,
+                })
+                .collect(),
 
             body: Some(return_job_request::ok::Body {
                 title: SELECTOR.select_title(&html),
@@ -206,23 +651,207 @@ async fn do_job(job: &GetJobResponse) -> anyhow::Result<return_job_request::Ok>
                 icon_url: SELECTOR
                     .select_icon_url(&html, &job_url)
                     .map(|url| url.to_string()),
-                text_fields: SELECTOR.select_text_fields(&html),
-                sections: SELECTOR.select_sections(&html),
+                text_fields,
+                sections,
                 keywords,
                 manifest,
                 images,
+                language,
+                json_ld: SELECTOR.select_json_ld(&html),
+                open_graph: SELECTOR.select_open_graph(&html, &job_url),
+                twitter_card: SELECTOR.select_twitter_card(&html, &job_url),
+                document_type: DocumentType::Html as i32,
+                author: SELECTOR.select_meta(&html, "author"),
+                page_count: None,
+                encoding: Some(encoding.name().to_owned()),
+                videos,
+                audios,
+            }),
+        }
+    } else if mime_type.contains("application/pdf") {
+        let bytes = fetcher.read_body(res).await?;
+
+        let pdf_url = page_url.clone();
+        let max_time = fetcher.pdf_time_limit();
+        let pdf = spawn_blocking(move || extract_pdf(&bytes, &pdf_url, max_time)).await??;
+
+        let language = detect_language(
+            None,
+            content_language.as_deref(),
+            &pdf.text_fields.join("\n"),
+        );
+
+        return_job_request::Ok {
+            status: status.as_u16() as i32,
+            mime_type,
+            etag,
+            last_modified,
+            final_url,
+            redirects,
+            feed: None,
+            feed_urls: vec![],
+            linked_urls: vec![],
+            anchors: vec![],
+
+            body: Some(return_job_request::ok::Body {
+                title: pdf.title,
+                description: pdf.subject,
+                text_fields: pdf.text_fields,
+                keywords: pdf.keywords,
+                language,
+                document_type: DocumentType::Pdf as i32,
+                author: pdf.author,
+                page_count: Some(pdf.page_count),
+                ..Default::default()
+            }),
+        }
+    } else if is_feed {
+        let bytes = fetcher.read_body(res).await?;
+
+        let job_url = page_url.clone();
+
+        let feed = spawn_blocking(move || parse_feed(&bytes, &job_url)).await??;
+
+        return_job_request::Ok {
+            etag,
+            last_modified,
+            final_url,
+            redirects,
+            ..feed_result(status.as_u16() as i32, mime_type, feed)
+        }
+    } else if let Some(document_type) = text_format {
+        let (mut text, encoding) = fetcher.read_text(res).await?;
+
+        let job_url = page_url.clone();
+
+        // Feeds are often served as plain XML, so try them before generic extraction.
+        if document_type == DocumentType::Xml {
+            let feed_url = job_url.clone();
+            let (text_back, feed) = spawn_blocking(move || {
+                let feed = parse_feed(text.as_bytes(), &feed_url);
+                (text, feed)
+            })
+            .await?;
+
+            if let Ok(feed) = feed {
+                return Ok(return_job_request::Ok {
+                    etag,
+                    last_modified,
+                    final_url,
+                    redirects,
+                    ..feed_result(status.as_u16() as i32, mime_type, feed)
+                });
+            }
+
+            text = text_back;
+        }
+
+        let document = spawn_blocking(move || match document_type {
+            DocumentType::Markdown => Ok(extract_markdown(&text, &job_url)),
+            DocumentType::Xml => extract_xml(&text, &job_url),
+            _ => Ok(extract_plain_text(&text, &job_url)),
+        })
+        .await??;
+
+        let language = detect_language(
+            None,
+            content_language.as_deref(),
+            &document.text_fields.join("\n"),
+        );
+
+        return_job_request::Ok {
+            status: status.as_u16() as i32,
+            mime_type,
+            etag,
+            last_modified,
+            final_url,
+            redirects,
+            feed: None,
+            feed_urls: vec![],
+            linked_urls: document
+                .linked_urls
+                .into_iter()
+                .map(|url| url.to_string())
+                .collect(),
+            anchors: document
+                .anchors
+                .into_iter()
+                .map(|(url, text)| return_job_request::ok::Anchor {
+                    url: url.to_string(),
+                    text,
+                })
+                .collect(),
+
+            body: Some(return_job_request::ok::Body {
+                title: document.title,
+                text_fields: document.text_fields,
+                sections: document.sections,
+                language,
+                document_type: document_type as i32,
+                encoding: Some(encoding.name().to_owned()),
+                ..Default::default()
             }),
         }
     } else {
         return_job_request::Ok {
             status: status.as_u16() as i32,
             mime_type,
+            etag,
+            last_modified,
+            final_url,
+            redirects,
+            feed: None,
+            feed_urls: vec![],
             body: None,
             linked_urls: vec![],
+            anchors: vec![],
         }
     })
 }
 
+fn header_string(headers: &HeaderMap, name: HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_owned())
+}
+
+fn feed_result(status: i32, mime_type: String, feed: FeedDocument) -> return_job_request::Ok {
+    return_job_request::Ok {
+        status,
+        mime_type,
+        linked_urls: vec![],
+        anchors: feed
+            .items
+            .iter()
+            .filter_map(|item| {
+                Some(return_job_request::ok::Anchor {
+                    url: item.url.to_string(),
+                    text: item.title.clone()?,
+                })
+            })
+            .collect(),
+        body: None,
+        etag: None,
+        last_modified: None,
+        final_url: String::new(),
+        redirects: vec![],
+        feed: Some(return_job_request::ok::Feed {
+            title: feed.title,
+            items: feed
+                .items
+                .into_iter()
+                .map(|item| return_job_request::ok::feed::Item {
+                    url: item.url.to_string(),
+                    title: item.title,
+                    published_at: item.published_at,
+                })
+                .collect(),
+        }),
+        feed_urls: vec![],
+    }
+}
+
 async fn get_job(client: &mut CrawlerClient<Channel>) -> Result<GetJobResponse, Status> {
     let backoff = Backoff::new(
         128,
@@ -255,74 +884,3 @@ struct Manifest {
     description: Option<String>,
     categories: Option<Vec<String>>,
 }
-
-// fn get_luminance_range_image(img: &DynamicImage) -> Option<LuminanceRange> {
-//     let mut min_luminance = f32::INFINITY;
-//     let mut max_luminance = -f32::INFINITY;
-//     let mut contact = false;
-
-//     for j in 0..img.height() {
-//         for i in 0..img.width() {
-//             let p = img.get_pixel(i, j);
-
-//             if p[3] > 127 {
-//                 contact = true;
-//                 let luminance = get_luminance(
-//                     p[0] as f32 / 255f32,
-//                     p[1] as f32 / 255f32,
-//                     p[2] as f32 / 255f32,
-//                 );
-//                 min_luminance = f32::min(min_luminance, luminance);
-//                 max_luminance = f32::max(max_luminance, luminance);
-//             }
-//         }
-//     }
-
-//     if contact {
-//         Some(LuminanceRange {
-//             min: min_luminance,
-//             max: max_luminance,
-//         })
-//     } else {
-//         None
-//     }
-// }
-
-// fn get_luminance_range_svg(img: &str) -> Option<LuminanceRange> {
-//     let parsed_svg = resvg::usvg::Tree::from_str(
-//         img,
-//         &resvg::usvg::Options::default(),
-//         &resvg::usvg::fontdb::Database::default(),
-//     )
-//     .ok()?;
-
-//     let mut pix_map = resvg::tiny_skia::Pixmap::new(1024, 1024).unwrap();
-
-//     resvg::render(
-//         &parsed_svg,
-//         resvg::usvg::Transform::default(),
-//         &mut pix_map.as_mut(),
-//     );
-
-//     let img = image::RgbaImage::from_vec(1024, 1024, pix_map.data().to_vec()).unwrap();
-
-//     img.save("test.png").unwrap();
-
-//     get_luminance_range_image(&img.into())
-// }
-
-// fn get_luminance(r: f32, g: f32, b: f32) -> f32 {
-//     let r = srgb_to_linear(r);
-//     let g = srgb_to_linear(g);
-//     let b = srgb_to_linear(b);
-
-//     0.2126 * r + 0.7152 * g + 0.0722 * b
-// }
-
-// fn srgb_to_linear(c: f32) -> f32 {
-//     if c <= 0.04045 {
-//         c / 12.92
-//     } else {
-//         f32::powf((c + 0.055) / 1.055, 2.4)
-//     }
-// }
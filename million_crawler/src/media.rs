@@ -0,0 +1,286 @@
+use std::io::Cursor;
+
+use reqwest::header::{HeaderMap, HeaderValue, RANGE};
+use symphonia::core::{
+    formats::FormatOptions, io::MediaSourceStream, meta::MetadataOptions, probe::Hint,
+};
+use tokio::task::spawn_blocking;
+use url::Url;
+
+use crate::fetch::Fetcher;
+
+/// Bytes fetched from the start of a media file to read its container headers.
+const MAX_PROBE_BYTES: usize = 4 * 1024 * 1024;
+
+/// Media elements probed per page; anything beyond this is stored without metadata.
+pub const MAX_PROBED_MEDIA: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaKind {
+    Video,
+    Audio,
+}
+
+/// A `<video>`, `<audio>` or `og:video`/`og:audio` reference found on a page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaElement {
+    pub kind: MediaKind,
+    pub url: Url,
+    pub title: Option<String>,
+    pub mime_type: Option<String>,
+    pub poster_url: Option<Url>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct MediaInfo {
+    pub length_millis: Option<u32>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+/// Fetches the start of a media file and reads its duration and, for MP4 video, its
+/// dimensions. Returns `None` for anything that is not a playable file (embed pages,
+/// streaming playlists) or that cannot be parsed from the first few megabytes.
+pub async fn probe_media(fetcher: &Fetcher, media: &MediaElement) -> Option<MediaInfo> {
+    if let Some(mime_type) = &media.mime_type {
+        if !(mime_type.starts_with("video/") || mime_type.starts_with("audio/"))
+            || mime_type.contains("mpegurl")
+        {
+            return None;
+        }
+    }
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        RANGE,
+        HeaderValue::from_str(&format!("bytes=0-{}", MAX_PROBE_BYTES - 1)).ok()?,
+    );
+
+    let res = fetcher
+        .get_with_headers(media.url.clone(), headers)
+        .await
+        .ok()?;
+
+    let content_type = res
+        .headers()
+        .get("Content-Type")
+        .and_then(|content_type| content_type.to_str().ok())
+        .map(|content_type| content_type.to_ascii_lowercase());
+
+    if content_type.as_deref().is_some_and(|content_type| {
+        !(content_type.starts_with("video/")
+            || content_type.starts_with("audio/")
+            || content_type.starts_with("application/octet-stream"))
+    }) {
+        return None;
+    }
+
+    let mut bytes = Vec::new();
+    let mut res = res;
+    while let Some(chunk) = res.chunk().await.ok()? {
+        bytes.extend_from_slice(&chunk);
+        if bytes.len() >= MAX_PROBE_BYTES {
+            bytes.truncate(MAX_PROBE_BYTES);
+            break;
+        }
+    }
+
+    let extension = media
+        .url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .and_then(|name| name.rsplit_once('.'))
+        .map(|(_, extension)| extension.to_ascii_lowercase());
+    let mime_type = content_type.or_else(|| media.mime_type.clone());
+
+    spawn_blocking(move || read_media_info(bytes, extension, mime_type))
+        .await
+        .ok()
+}
+
+fn read_media_info(
+    bytes: Vec<u8>,
+    extension: Option<String>,
+    mime_type: Option<String>,
+) -> MediaInfo {
+    let (width, height) = mp4_dimensions(&bytes).unzip();
+
+    let mut hint = Hint::new();
+    if let Some(extension) = &extension {
+        hint.with_extension(extension);
+    }
+    if let Some(mime_type) = &mime_type {
+        hint.mime_type(mime_type.split(';').next().unwrap_or_default().trim());
+    }
+
+    let source = MediaSourceStream::new(Box::new(Cursor::new(bytes)), Default::default());
+
+    let length_millis = symphonia::default::get_probe()
+        .format(
+            &hint,
+            source,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .ok()
+        .and_then(|probed| {
+            probed.format.tracks().iter().find_map(|track| {
+                let params = &track.codec_params;
+                let frames = params.n_frames?;
+
+                let seconds = match params.time_base {
+                    Some(time_base) => {
+                        let time = time_base.calc_time(frames);
+                        time.seconds as f64 + time.frac
+                    }
+                    None => frames as f64 / params.sample_rate? as f64,
+                };
+
+                Some((seconds * 1000.0).round() as u32)
+            })
+        });
+
+    MediaInfo {
+        length_millis,
+        width,
+        height,
+    }
+}
+
+/// Reads the display size of the first video track from an MP4 `moov/trak/tkhd` box.
+/// Files not written for streaming keep `moov` after the media data, past the probed
+/// bytes, so their size stays unknown unless the page declares it.
+fn mp4_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    let moov = mp4_boxes(bytes).find(|(name, _)| name == b"moov")?.1;
+
+    mp4_boxes(moov)
+        .filter(|(name, _)| name == b"trak")
+        .filter_map(|(_, trak)| mp4_boxes(trak).find(|(name, _)| name == b"tkhd"))
+        .find_map(|(_, tkhd)| {
+            // Version 1 headers use 64-bit times and durations.
+            let times = if *tkhd.first()? == 1 { 32 } else { 20 };
+            let offset = 4 + times + 52;
+
+            let fixed = |at: usize| -> Option<u32> {
+                let value = u32::from_be_bytes(tkhd.get(at..at + 4)?.try_into().ok()?);
+                Some(value >> 16)
+            };
+
+            let (width, height) = (fixed(offset)?, fixed(offset + 4)?);
+            (width > 0 && height > 0).then_some((width, height))
+        })
+}
+
+/// Iterates the boxes directly inside `bytes`, yielding each box type and its payload.
+fn mp4_boxes(mut bytes: &[u8]) -> impl Iterator<Item = (&[u8], &[u8])> {
+    std::iter::from_fn(move || {
+        let size = u32::from_be_bytes(bytes.get(0..4)?.try_into().ok()?) as u64;
+        let name = bytes.get(4..8)?;
+
+        let (header, size) = match size {
+            0 => (8, bytes.len() as u64),
+            1 => (16, u64::from_be_bytes(bytes.get(8..16)?.try_into().ok()?)),
+            size => (8, size),
+        };
+
+        let size = usize::try_from(size).ok()?;
+        if size < header {
+            return None;
+        }
+
+        let payload = bytes.get(header..size.min(bytes.len()))?;
+        bytes = bytes.get(size..).unwrap_or_default();
+
+        Some((name, payload))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mp4_box(name: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut bytes = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+        bytes.extend_from_slice(name);
+        bytes.extend_from_slice(payload);
+        bytes
+    }
+
+    fn tkhd(version: u8, width: u32, height: u32) -> Vec<u8> {
+        let times = if version == 1 { 32 } else { 20 };
+
+        let mut payload = vec![version, 0, 0, 0];
+        payload.resize(4 + times + 52, 0);
+        payload.extend_from_slice(&(width << 16).to_be_bytes());
+        payload.extend_from_slice(&(height << 16).to_be_bytes());
+        mp4_box(b"tkhd", &payload)
+    }
+
+    fn movie(traks: &[Vec<u8>]) -> Vec<u8> {
+        let mut moov = mp4_box(b"mvhd", &[0; 100]);
+        for trak in traks {
+            moov.extend(mp4_box(b"trak", trak));
+        }
+
+        let mut bytes = mp4_box(b"ftyp", b"isom\0\0\0\0");
+        bytes.extend(mp4_box(b"moov", &moov));
+        bytes
+    }
+
+    #[test]
+    fn reads_first_video_track_size() {
+        // Audio tracks have a zero size and are skipped.
+        let bytes = movie(&[tkhd(0, 0, 0), tkhd(0, 1280, 720), tkhd(0, 640, 360)]);
+        assert_eq!(mp4_dimensions(&bytes), Some((1280, 720)));
+
+        let bytes = movie(&[tkhd(1, 1920, 1080)]);
+        assert_eq!(mp4_dimensions(&bytes), Some((1920, 1080)));
+    }
+
+    #[test]
+    fn reads_large_and_open_ended_boxes() {
+        let moov = mp4_box(b"trak", &tkhd(0, 320, 240));
+
+        let mut large = 1u32.to_be_bytes().to_vec();
+        large.extend_from_slice(b"moov");
+        large.extend_from_slice(&((moov.len() + 16) as u64).to_be_bytes());
+        large.extend_from_slice(&moov);
+        assert_eq!(mp4_dimensions(&large), Some((320, 240)));
+
+        let mut open_ended = 0u32.to_be_bytes().to_vec();
+        open_ended.extend_from_slice(b"moov");
+        open_ended.extend_from_slice(&moov);
+        assert_eq!(mp4_dimensions(&open_ended), Some((320, 240)));
+    }
+
+    #[test]
+    fn misses_moov_after_truncated_media_data() {
+        // `mdat` claims more bytes than were probed, so `moov` is never reached.
+        let mut bytes = mp4_box(b"ftyp", b"isom");
+        bytes.extend_from_slice(&(1u32 << 30).to_be_bytes());
+        bytes.extend_from_slice(b"mdat");
+        bytes.extend_from_slice(&[0; 64]);
+
+        assert_eq!(mp4_dimensions(&bytes), None);
+    }
+
+    #[test]
+    fn rejects_malformed_boxes() {
+        assert_eq!(mp4_boxes(b"").count(), 0);
+        assert_eq!(mp4_boxes(b"\0\0\0").count(), 0);
+        // A box smaller than its own header ends the iteration.
+        assert_eq!(mp4_boxes(b"\0\0\0\x04moov").count(), 0);
+
+        let mut truncated = tkhd(0, 1280, 720);
+        truncated.truncate(truncated.len() - 6);
+        assert_eq!(mp4_dimensions(&movie(&[truncated])), None);
+
+        let mut cut = movie(&[tkhd(0, 1280, 720)]);
+        cut.truncate(cut.len() - 6);
+        assert_eq!(mp4_dimensions(&cut), None);
+
+        assert_eq!(mp4_dimensions(&mp4_box(b"moov", &[])), None);
+    }
+}
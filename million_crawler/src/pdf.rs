@@ -0,0 +1,151 @@
+use std::time::{Duration, Instant};
+
+use lopdf::{decode_text_string, Dictionary, Document};
+use pdf_extract::{output_doc_page, PlainTextOutput};
+use proto::crawler::Guard;
+use url::Url;
+
+use crate::fetch::GuardError;
+
+#[derive(Debug, Default)]
+pub struct Pdf {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub subject: Option<String>,
+    pub keywords: Vec<String>,
+    pub page_count: u32,
+    pub text_fields: Vec<String>,
+}
+
+/// Reads the document information dictionary and the text of every page, stopping
+/// with a [`Guard::Timeout`] once the pages have taken longer than `max_time`.
+pub fn extract_pdf(bytes: &[u8], url: &Url, max_time: Duration) -> anyhow::Result<Pdf> {
+    let started = Instant::now();
+
+    let mut document = Document::load_mem(bytes)?;
+    // An empty user password only restricts what readers may do, not what they can read.
+    if document.is_encrypted() {
+        document.decrypt("")?;
+    }
+
+    let info = document
+        .trailer
+        .get(b"Info")
+        .and_then(|info| document.dereference(info))
+        .and_then(|(_, info)| info.as_dict())
+        .ok();
+
+    let page_numbers = document.get_pages().into_keys().collect::<Vec<_>>();
+
+    let mut pages = Vec::new();
+    for page_number in &page_numbers {
+        if started.elapsed() > max_time {
+            return Err(GuardError {
+                guard: Guard::Timeout,
+                url: url.to_string(),
+            }
+            .into());
+        }
+
+        // A page that can't be read shouldn't cost the text of the others.
+        let mut text = String::new();
+        if output_doc_page(
+            &document,
+            &mut PlainTextOutput::new(&mut text),
+            *page_number,
+        )
+        .is_ok()
+        {
+            pages.push(text);
+        }
+    }
+
+    let text_fields = pages
+        .iter()
+        .flat_map(|page| page.split("\n\n"))
+        .map(|paragraph| paragraph.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|paragraph| !paragraph.is_empty())
+        .collect::<Vec<_>>();
+
+    let title = info
+        .and_then(|info| info_string(info, b"Title"))
+        .or_else(|| {
+            text_fields
+                .first()
+                .map(|first| first.chars().take(200).collect())
+        });
+
+    Ok(Pdf {
+        title,
+        author: info.and_then(|info| info_string(info, b"Author")),
+        subject: info.and_then(|info| info_string(info, b"Subject")),
+        keywords: info
+            .and_then(|info| info_string(info, b"Keywords"))
+            .map(|keywords| {
+                keywords
+                    .split([',', ';'])
+                    .map(|keyword| keyword.trim().to_owned())
+                    .filter(|keyword| !keyword.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default(),
+        page_count: page_numbers.len() as u32,
+        text_fields,
+    })
+}
+
+fn info_string(info: &Dictionary, key: &[u8]) -> Option<String> {
+    info.get(key)
+        .ok()
+        .and_then(|value| decode_text_string(value).ok())
+        .map(|value| value.trim().to_owned())
+        .filter(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fetch::tripped_guard;
+
+    use super::*;
+
+    const WITH_INFO: &[u8] = include_bytes!("../tests/fixtures/pdf/with_info.pdf");
+    const WITHOUT_INFO: &[u8] = include_bytes!("../tests/fixtures/pdf/without_info.pdf");
+
+    fn url() -> Url {
+        Url::parse("https://example.com/handbook.pdf").unwrap()
+    }
+
+    #[test]
+    fn reads_info_and_pages() {
+        let pdf = extract_pdf(WITH_INFO, &url(), Duration::from_secs(30)).unwrap();
+
+        assert_eq!(pdf.title.as_deref(), Some("Crawler Handbook"));
+        assert_eq!(pdf.author.as_deref(), Some("Million Search"));
+        assert_eq!(pdf.subject.as_deref(), Some("How pages are crawled"));
+        assert_eq!(pdf.keywords, ["crawler", "search", "index"]);
+        assert_eq!(pdf.page_count, 2);
+        assert_eq!(pdf.text_fields, ["Crawling the web", "Second page"]);
+    }
+
+    #[test]
+    fn titles_a_pdf_without_info_by_its_first_text() {
+        let pdf = extract_pdf(WITHOUT_INFO, &url(), Duration::from_secs(30)).unwrap();
+
+        assert_eq!(pdf.title.as_deref(), Some("A document without metadata"));
+        assert_eq!(pdf.author, None);
+        assert!(pdf.keywords.is_empty());
+        assert_eq!(pdf.page_count, 1);
+    }
+
+    #[test]
+    fn stops_at_the_time_limit() {
+        let err = extract_pdf(WITH_INFO, &url(), Duration::ZERO).unwrap_err();
+
+        assert_eq!(tripped_guard(&err), Some(Guard::Timeout));
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(extract_pdf(b"<html></html>", &url(), Duration::from_secs(30)).is_err());
+    }
+}
@@ -0,0 +1,385 @@
+use std::{str::FromStr, sync::Arc, time::Duration};
+
+use anyhow::anyhow;
+use chromiumoxide::{
+    cdp::browser_protocol::{
+        fetch::{ContinueRequestParams, EnableParams, EventRequestPaused, FailRequestParams},
+        network::ErrorReason,
+    },
+    Browser, BrowserConfig, Page,
+};
+use futures::StreamExt;
+use million_common::net::AddressFilter;
+use tokio::task::{spawn_blocking, JoinHandle};
+use url::Url;
+
+use crate::{content::extract_main_content, SELECTOR};
+
+/// Settings for rendering script-built pages in a headless browser. Rendering is off
+/// unless a browser is given with `--chrome-url` or `--chrome-executable`.
+#[derive(clap::Args, Debug, Clone)]
+pub struct RenderOptions {
+    /// DevTools endpoint of a running Chrome, e.g. `http://localhost:9222`. Every request
+    /// the browser makes is checked against `--allowed-networks`, but it resolves hosts
+    /// again itself, so still run it where it cannot reach internal services.
+    #[arg(long, env)]
+    pub chrome_url: Option<String>,
+
+    /// Chrome or Chromium binary to launch when no `--chrome-url` is given.
+    #[arg(long, env)]
+    pub chrome_executable: Option<String>,
+
+    /// Pages whose extracted text is shorter than this many characters are rendered.
+    #[arg(long, env, default_value_t = 200)]
+    pub render_min_text_chars: usize,
+
+    #[arg(long, env, default_value_t = 20)]
+    pub render_timeout_secs: u64,
+
+    /// Largest rendered document kept, in bytes of serialized HTML.
+    #[arg(long, env, default_value_t = 16 * 1024 * 1024)]
+    pub render_max_html_bytes: usize,
+
+    /// Per-site overrides as `host[/path]=always|never|auto`, e.g. `app.example.com=always`
+    /// or `example.com/app=always`. A leading `*.` also matches subdomains, and a path
+    /// limits the rule to that path and below it. The first matching rule wins.
+    #[arg(long, env, value_delimiter = ',')]
+    pub render_rules: Vec<RenderRule>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderMode {
+    /// Render when the fetched HTML yields too little text.
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderRule {
+    host: String,
+    include_subdomains: bool,
+    /// Path prefix, without a trailing slash, matched at segment boundaries.
+    path: Option<String>,
+    mode: RenderMode,
+}
+
+impl RenderRule {
+    fn matches(&self, host: &str, path: &str) -> bool {
+        let host_matches = host == self.host
+            || (self.include_subdomains
+                && host
+                    .strip_suffix(&self.host)
+                    .is_some_and(|prefix| prefix.ends_with('.')));
+
+        host_matches
+            && self.path.as_deref().is_none_or(|prefix| {
+                path.strip_prefix(prefix)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+            })
+    }
+}
+
+impl FromStr for RenderRule {
+    type Err = anyhow::Error;
+
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let (host, mode) = rule
+            .split_once('=')
+            .ok_or_else(|| anyhow!("expected `host=mode`, got `{}`", rule))?;
+
+        let mode = match mode.trim() {
+            "auto" => RenderMode::Auto,
+            "always" => RenderMode::Always,
+            "never" => RenderMode::Never,
+            mode => return Err(anyhow!("unknown render mode `{}`", mode)),
+        };
+
+        let (host, path) = match host.trim().split_once('/') {
+            Some((host, path)) => (host, Some(path.trim_end_matches('/'))),
+            None => (host.trim(), None),
+        };
+
+        let host = host.to_ascii_lowercase();
+        let (host, include_subdomains) = match host.strip_prefix("*.") {
+            Some(host) => (host.to_owned(), true),
+            None => (host, false),
+        };
+        if host.is_empty() {
+            return Err(anyhow!("render rule `{}` has no host", rule));
+        }
+
+        Ok(Self {
+            host,
+            include_subdomains,
+            path: path
+                .filter(|path| !path.is_empty())
+                .map(|path| format!("/{}", path)),
+            mode,
+        })
+    }
+}
+
+/// Renders pages through the Chrome DevTools Protocol. Without a browser every page is
+/// left as fetched.
+#[derive(Clone)]
+pub struct Renderer {
+    browser: Option<Arc<Browser>>,
+    options: RenderOptions,
+    user_agent: String,
+    filter: AddressFilter,
+}
+
+impl Renderer {
+    /// Connects to or launches the configured browser. A browser that cannot be reached
+    /// only disables rendering, since plain fetching still works.
+    pub async fn new(options: RenderOptions, user_agent: String, filter: AddressFilter) -> Self {
+        let browser = match connect(&options).await {
+            Ok(browser) => browser.map(Arc::new),
+            Err(err) => {
+                tracing::warn!("rendering disabled, could not start browser: {}", err);
+                None
+            }
+        };
+
+        Self {
+            browser,
+            options,
+            user_agent,
+            filter,
+        }
+    }
+
+    /// Returns the page's HTML after scripts have run, or `None` when the fetched HTML
+    /// should be used as is: rendering is off for the host, the page already has enough
+    /// text, or the browser failed.
+    pub async fn render_if_needed(&self, url: &Url, html: &str) -> Option<String> {
+        let browser = self.browser.as_ref()?;
+
+        match self.mode(url) {
+            RenderMode::Never => return None,
+            RenderMode::Always => {}
+            RenderMode::Auto => {
+                let html = html.to_owned();
+                let text_len = spawn_blocking(move || extracted_text_len(&html))
+                    .await
+                    .ok()?;
+                if text_len >= self.options.render_min_text_chars {
+                    return None;
+                }
+            }
+        }
+
+        match self.render(browser, url).await {
+            Ok(html) => Some(html),
+            Err(err) => {
+                tracing::warn!("rendering {} failed: {}", url, err);
+                None
+            }
+        }
+    }
+
+    fn mode(&self, url: &Url) -> RenderMode {
+        let host = url.host_str().unwrap_or_default().to_ascii_lowercase();
+
+        self.options
+            .render_rules
+            .iter()
+            .find(|rule| rule.matches(&host, url.path()))
+            .map_or(RenderMode::Auto, |rule| rule.mode)
+    }
+
+    async fn render(&self, browser: &Browser, url: &Url) -> anyhow::Result<String> {
+        let timeout = Duration::from_secs(self.options.render_timeout_secs);
+        let timed_out = |_| anyhow!("timed out after {:?}", timeout);
+
+        let page = tokio::time::timeout(timeout, browser.new_page("about:blank"))
+            .await
+            .map_err(timed_out)??;
+
+        // The page is closed however loading ends, so a timeout does not leave it running
+        // in the browser.
+        let mut interceptor = None;
+        let result = tokio::time::timeout(timeout, async {
+            interceptor = Some(self.intercept_requests(&page).await?);
+            page.set_user_agent(self.user_agent.as_str()).await?;
+            page.goto(url.as_str()).await?;
+            self.content(&page).await
+        })
+        .await
+        .map_err(timed_out);
+
+        if let Some(interceptor) = interceptor {
+            interceptor.abort();
+        }
+        if let Err(err) = page.close().await {
+            tracing::debug!("closing rendered page for {} failed: {}", url, err);
+        }
+
+        result?
+    }
+
+    /// Pauses every request the page makes, including redirects and subresources, and
+    /// only lets through those whose host passes the address filter.
+    async fn intercept_requests(&self, page: &Page) -> anyhow::Result<JoinHandle<()>> {
+        let mut paused = page.event_listener::<EventRequestPaused>().await?;
+        page.execute(EnableParams::default()).await?;
+
+        let page = page.clone();
+        let filter = self.filter.clone();
+        Ok(tokio::spawn(async move {
+            while let Some(event) = paused.next().await {
+                let allowed = match Url::parse(&event.request.url) {
+                    Ok(url) => filter.check_resolved(&url).await.is_ok(),
+                    Err(_) => false,
+                };
+
+                let reply = if allowed {
+                    page.execute(ContinueRequestParams::new(event.request_id.clone()))
+                        .await
+                        .map(drop)
+                } else {
+                    tracing::debug!("browser request to {} blocked", event.request.url);
+                    page.execute(FailRequestParams::new(
+                        event.request_id.clone(),
+                        ErrorReason::BlockedByClient,
+                    ))
+                    .await
+                    .map(drop)
+                };
+                if let Err(err) = reply {
+                    tracing::debug!("answering paused browser request failed: {}", err);
+                }
+            }
+        }))
+    }
+
+    /// The rendered document, refused before it is transferred when it is larger than
+    /// `--render-max-html-bytes`.
+    async fn content(&self, page: &Page) -> anyhow::Result<String> {
+        let len = page
+            .evaluate("document.documentElement.outerHTML.length")
+            .await?
+            .into_value::<usize>()?;
+        // `length` counts UTF-16 units, which never outnumber the UTF-8 bytes, so this only
+        // refuses documents that are certainly too large.
+        if len > self.options.render_max_html_bytes {
+            anyhow::bail!(
+                "rendered document is larger than {} bytes",
+                self.options.render_max_html_bytes
+            );
+        }
+
+        let html = page.content().await?;
+        if html.len() > self.options.render_max_html_bytes {
+            anyhow::bail!(
+                "rendered document is larger than {} bytes",
+                self.options.render_max_html_bytes
+            );
+        }
+
+        Ok(html)
+    }
+}
+
+async fn connect(options: &RenderOptions) -> anyhow::Result<Option<Browser>> {
+    let (browser, mut handler) = if let Some(chrome_url) = &options.chrome_url {
+        Browser::connect(chrome_url.as_str()).await?
+    } else if let Some(executable) = &options.chrome_executable {
+        let config = BrowserConfig::builder()
+            .chrome_executable(executable)
+            .build()
+            .map_err(|err| anyhow!(err))?;
+        Browser::launch(config).await?
+    } else {
+        return Ok(None);
+    };
+
+    // The handler drives the DevTools connection and has to be polled for the browser to
+    // make progress.
+    tokio::spawn(async move {
+        while let Some(event) = handler.next().await {
+            if let Err(err) = event {
+                tracing::debug!("browser connection error: {}", err);
+            }
+        }
+    });
+
+    Ok(Some(browser))
+}
+
+/// Characters of text the extractor finds in `html`, measured the same way the crawl
+/// result is built.
+fn extracted_text_len(html: &str) -> usize {
+    let html = scraper::Html::parse_document(html);
+
+    let text_fields = match extract_main_content(&html) {
+        Some(content) => content.text_fields,
+        None => SELECTOR.select_text_fields(&html),
+    };
+
+    text_fields.iter().map(|text| text.chars().count()).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(rule: &str) -> RenderRule {
+        rule.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_rules() {
+        assert_eq!(
+            rule(" App.Example.com = always "),
+            RenderRule {
+                host: String::from("app.example.com"),
+                include_subdomains: false,
+                path: None,
+                mode: RenderMode::Always,
+            }
+        );
+        assert_eq!(
+            rule("*.example.com/Docs/=never"),
+            RenderRule {
+                host: String::from("example.com"),
+                include_subdomains: true,
+                path: Some(String::from("/Docs")),
+                mode: RenderMode::Never,
+            }
+        );
+        assert_eq!(rule("example.com/=auto").path, None);
+
+        assert!("example.com".parse::<RenderRule>().is_err());
+        assert!("example.com=sometimes".parse::<RenderRule>().is_err());
+        assert!("=always".parse::<RenderRule>().is_err());
+        assert!("*./app=always".parse::<RenderRule>().is_err());
+    }
+
+    #[test]
+    fn matches_hosts_and_subdomains() {
+        let exact = rule("example.com=always");
+        assert!(exact.matches("example.com", "/"));
+        assert!(!exact.matches("app.example.com", "/"));
+
+        let subdomains = rule("*.example.com=always");
+        assert!(subdomains.matches("example.com", "/"));
+        assert!(subdomains.matches("app.example.com", "/"));
+        assert!(subdomains.matches("a.b.example.com", "/"));
+        assert!(!subdomains.matches("badexample.com", "/"));
+        assert!(!subdomains.matches("example.com.evil.net", "/"));
+    }
+
+    #[test]
+    fn matches_path_prefixes_at_segment_boundaries() {
+        let app = rule("example.com/app=always");
+
+        assert!(app.matches("example.com", "/app"));
+        assert!(app.matches("example.com", "/app/"));
+        assert!(app.matches("example.com", "/app/settings"));
+        assert!(!app.matches("example.com", "/apple"));
+        assert!(!app.matches("example.com", "/"));
+        assert!(!app.matches("other.com", "/app"));
+    }
+}
@@ -1,6 +1,18 @@
-use scraper::{Html, Selector};
+use proto::crawler::return_job_request::ok::body::{OpenGraph, TwitterCard};
+use scraper::{ElementRef, Html, Selector};
 use url::Url;
 
+use crate::{
+    image_context::{element_text, filename_tokens, ImageContext, ImageElement, ImageVariant},
+    media::{MediaElement, MediaKind},
+    srcset::{parse_srcset, Descriptor},
+};
+
+/// Attributes lazy-loading scripts move the real image URL into.
+const LAZY_SRC_ATTRIBUTES: &[&str] = &["data-src", "data-lazy-src", "data-original"];
+
+const LAZY_SRCSET_ATTRIBUTES: &[&str] = &["data-srcset", "data-lazy-srcset"];
+
 pub struct SelectorSet {
     href_selector: Selector,
     codebase_selector: Selector,
@@ -33,6 +45,20 @@ pub struct SelectorSet {
     link_manifest_selector: Selector,
 
     images_selector: Selector,
+    figcaption_selector: Selector,
+    paragraph_selector: Selector,
+
+    anchor_selector: Selector,
+
+    html_lang_selector: Selector,
+
+    meta_selector: Selector,
+    json_ld_selector: Selector,
+
+    feed_link_selector: Selector,
+
+    media_selector: Selector,
+    media_source_selector: Selector,
 }
 
 impl SelectorSet {
@@ -71,7 +97,21 @@ impl SelectorSet {
 
             link_manifest_selector: Selector::parse("link[rel=\"manifest\"][href]").unwrap(),
 
-            images_selector: Selector::parse("img[src]").unwrap(),
+            images_selector: Selector::parse("img").unwrap(),
+            figcaption_selector: Selector::parse("figcaption").unwrap(),
+            paragraph_selector: Selector::parse("p").unwrap(),
+
+            anchor_selector: Selector::parse("a[href]").unwrap(),
+
+            html_lang_selector: Selector::parse("html[lang]").unwrap(),
+
+            meta_selector: Selector::parse("meta[content]").unwrap(),
+            json_ld_selector: Selector::parse("script[type=\"application/ld+json\"]").unwrap(),
+
+            feed_link_selector: Selector::parse("link[rel~=\"alternate\"][type][href]").unwrap(),
+
+            media_selector: Selector::parse("video, audio").unwrap(),
+            media_source_selector: Selector::parse("source[src]").unwrap(),
         }
     }
 
@@ -82,37 +122,376 @@ impl SelectorSet {
             .and_then(|link_manifest_url| Self::normalize_url(link_manifest_url, page_url).ok())
     }
 
-    pub fn select_images(&self, doc: &Html, page_url: &Url) -> Vec<(Url, Option<String>)> {
+    pub fn select_images(&self, doc: &Html, page_url: &Url) -> Vec<ImageElement> {
         doc.select(&self.images_selector)
-            .map(|image| (image.attr("src").unwrap(), image.attr("alt")))
-            .filter_map(|(image_url, image_alt_text)| {
-                Some((
-                    Self::normalize_url(image_url, page_url).ok()?,
-                    image_alt_text.map(|iat| iat.to_owned()),
-                ))
+            .filter_map(|image| {
+                let size = |name| image.attr(name).and_then(|size| size.trim().parse().ok());
+                let width = size("width");
+
+                let variants = Self::image_variants(image, page_url);
+                let url = Self::largest_variant(&variants, width)?.url.clone();
+
+                Some(ImageElement {
+                    alt_text: image
+                        .attr("alt")
+                        .map(|alt_text| alt_text.trim().to_owned())
+                        .filter(|alt_text| !alt_text.is_empty()),
+                    context: ImageContext {
+                        caption: self.image_caption(image),
+                        title: image
+                            .attr("title")
+                            .map(|title| title.trim().to_owned())
+                            .filter(|title| !title.is_empty()),
+                        heading: self.image_heading(image),
+                        paragraph: self.image_paragraph(image),
+                        filename_tokens: filename_tokens(&url),
+                    },
+                    width,
+                    height: size("height"),
+                    url,
+                    variants,
+                })
             })
             .collect()
     }
 
+    /// Every URL an `<img>` may load, in the order `src`, `srcset`, the sources of an
+    /// enclosing `<picture>`, then lazy-loading attributes. Inline `data:` placeholders
+    /// are left out.
+    fn image_variants(image: ElementRef, page_url: &Url) -> Vec<ImageVariant> {
+        let picture_sources = image
+            .parent()
+            .and_then(ElementRef::wrap)
+            .filter(|parent| parent.value().name() == "picture")
+            .into_iter()
+            .flat_map(|picture| picture.children().filter_map(ElementRef::wrap))
+            .filter(|child| child.value().name() == "source");
+
+        let srcsets = std::iter::once(image)
+            .chain(picture_sources)
+            .flat_map(|element| {
+                std::iter::once("srcset")
+                    .chain(LAZY_SRCSET_ATTRIBUTES.iter().copied())
+                    .filter_map(move |name| element.attr(name))
+            })
+            .flat_map(parse_srcset)
+            .map(|candidate| (candidate.url, candidate.descriptor));
+
+        let candidates = image
+            .attr("src")
+            .map(|src| (src, Descriptor::None))
+            .into_iter()
+            .chain(srcsets)
+            .chain(
+                LAZY_SRC_ATTRIBUTES
+                    .iter()
+                    .filter_map(|name| image.attr(name))
+                    .map(|src| (src, Descriptor::None)),
+            );
+
+        let mut variants: Vec<ImageVariant> = Vec::new();
+
+        for (url, descriptor) in candidates {
+            let url = url.trim();
+            if url.is_empty() || url.starts_with("data:") {
+                continue;
+            }
+
+            let Ok(url) = Self::normalize_url(url, page_url) else {
+                continue;
+            };
+
+            let (width, density) = match descriptor {
+                Descriptor::None => (None, None),
+                Descriptor::Width(width) => (Some(width), None),
+                Descriptor::Density(density) => (None, Some(density)),
+            };
+
+            // A later mention of the same URL only adds what it knows about the size.
+            match variants.iter_mut().find(|variant| variant.url == url) {
+                Some(variant) => {
+                    variant.width = variant.width.or(width);
+                    variant.density = variant.density.or(density);
+                }
+                None => variants.push(ImageVariant {
+                    url,
+                    width,
+                    density,
+                }),
+            }
+        }
+
+        variants
+    }
+
+    /// The variant with the most pixels across. `w` descriptors are exact widths, while
+    /// densities are scaled by the `width` attribute when there is one. On a tie the
+    /// later variant wins, so a lazy-loaded URL beats the placeholder in `src`.
+    fn largest_variant(variants: &[ImageVariant], width: Option<u32>) -> Option<&ImageVariant> {
+        let base_width = width.unwrap_or(1) as f32;
+
+        variants.iter().max_by(|a, b| {
+            let size = |variant: &ImageVariant| match variant.width {
+                Some(width) => width as f32,
+                None => variant.density.unwrap_or(1.0) * base_width,
+            };
+
+            size(a).total_cmp(&size(b))
+        })
+    }
+
+    /// The `<figcaption>` of the closest enclosing `<figure>`.
+    fn image_caption(&self, image: ElementRef) -> Option<String> {
+        image
+            .ancestors()
+            .filter_map(ElementRef::wrap)
+            .find(|ancestor| ancestor.value().name() == "figure")?
+            .select(&self.figcaption_selector)
+            .next()
+            .and_then(element_text)
+    }
+
+    /// The last heading before the image in document order.
+    fn image_heading(&self, image: ElementRef) -> Option<String> {
+        let mut node = *image;
+
+        loop {
+            for sibling in node.prev_siblings().filter_map(ElementRef::wrap) {
+                if self.sections_selector.matches(&sibling) {
+                    return element_text(sibling);
+                }
+                if let Some(heading) = sibling.select(&self.sections_selector).last() {
+                    return element_text(heading);
+                }
+            }
+
+            node = node.parent()?;
+        }
+    }
+
+    /// The paragraph containing the image, otherwise the paragraph right after or before
+    /// the image or its parent.
+    fn image_paragraph(&self, image: ElementRef) -> Option<String> {
+        if let Some(paragraph) = image
+            .ancestors()
+            .filter_map(ElementRef::wrap)
+            .find(|ancestor| self.paragraph_selector.matches(ancestor))
+        {
+            return element_text(paragraph);
+        }
+
+        let parent = image.parent().and_then(ElementRef::wrap);
+
+        [Some(image), parent]
+            .into_iter()
+            .flatten()
+            .find_map(|element| {
+                let next = element.next_siblings().find_map(ElementRef::wrap);
+                let prev = element.prev_siblings().find_map(ElementRef::wrap);
+
+                [next, prev]
+                    .into_iter()
+                    .flatten()
+                    .find(|sibling| self.paragraph_selector.matches(sibling))
+            })
+            .and_then(element_text)
+    }
+
+    /// Every `<video>` and `<audio>` element, using its `src` or first `<source>`, followed
+    /// by the page's `og:video` and `og:audio` if they point somewhere new.
+    pub fn select_media(&self, doc: &Html, page_url: &Url) -> Vec<MediaElement> {
+        let mut media = doc
+            .select(&self.media_selector)
+            .filter_map(|element| {
+                let kind = match element.value().name() {
+                    "video" => MediaKind::Video,
+                    _ => MediaKind::Audio,
+                };
+
+                let (src, mime_type) = match element.attr("src") {
+                    Some(src) => (src, None),
+                    None => {
+                        let source = element.select(&self.media_source_selector).next()?;
+                        (source.attr("src").unwrap(), source.attr("type"))
+                    }
+                };
+
+                let size = |name| element.attr(name).and_then(|size| size.trim().parse().ok());
+
+                Some(MediaElement {
+                    kind,
+                    url: Self::normalize_url(src, page_url).ok()?,
+                    title: element
+                        .attr("title")
+                        .or_else(|| element.attr("aria-label"))
+                        .map(|title| title.trim().to_owned())
+                        .filter(|title| !title.is_empty()),
+                    mime_type: mime_type.map(|mime_type| mime_type.trim().to_ascii_lowercase()),
+                    poster_url: element
+                        .attr("poster")
+                        .and_then(|poster| Self::normalize_url(poster, page_url).ok()),
+                    width: size("width"),
+                    height: size("height"),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for (kind, prefix) in [
+            (MediaKind::Video, "og:video"),
+            (MediaKind::Audio, "og:audio"),
+        ] {
+            let Some(url) = self
+                .select_meta_url(doc, &format!("{}:secure_url", prefix), page_url)
+                .or_else(|| self.select_meta_url(doc, &format!("{}:url", prefix), page_url))
+                .or_else(|| self.select_meta_url(doc, prefix, page_url))
+            else {
+                continue;
+            };
+
+            if media.iter().any(|media| media.url == url) {
+                continue;
+            }
+
+            let meta = |name: &str| self.select_meta(doc, &format!("{}:{}", prefix, name));
+
+            media.push(MediaElement {
+                kind,
+                url,
+                title: self.select_meta(doc, "og:title"),
+                mime_type: meta("type").map(|mime_type| mime_type.to_ascii_lowercase()),
+                poster_url: (kind == MediaKind::Video)
+                    .then(|| self.select_meta_url(doc, "og:image", page_url))
+                    .flatten(),
+                width: meta("width").and_then(|width| width.parse().ok()),
+                height: meta("height").and_then(|height| height.parse().ok()),
+            });
+        }
+
+        media
+    }
+
+    pub fn select_feed_urls(&self, doc: &Html, page_url: &Url) -> Vec<Url> {
+        doc.select(&self.feed_link_selector)
+            .filter(|link| {
+                matches!(
+                    link.attr("type")
+                        .unwrap()
+                        .trim()
+                        .to_ascii_lowercase()
+                        .as_str(),
+                    "application/rss+xml" | "application/atom+xml" | "application/feed+json"
+                )
+            })
+            .filter_map(|link| Self::normalize_url(link.attr("href").unwrap(), page_url).ok())
+            .collect()
+    }
+
+    /// The first `<title>`, with runs of whitespace collapsed as browsers display it.
     pub fn select_title(&self, doc: &Html) -> Option<String> {
         doc.select(&self.title_selector)
             .next()
-            .map(|title| title.text().map(|text| text.to_string()).collect())
+            .map(|title| {
+                title
+                    .text()
+                    .flat_map(|text| text.split_whitespace())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .filter(|title| !title.is_empty())
     }
 
     pub fn select_keywords(&self, doc: &Html) -> Vec<String> {
         doc.select(&self.keyword_selector)
             .next()
-            .and_then(|description| description.attr("content"))
-            .map(|description| description.split(',').map(|text| text.to_owned()).collect())
+            .and_then(|keywords| keywords.attr("content"))
+            .map(|keywords| {
+                keywords
+                    .split(',')
+                    .map(|keyword| keyword.trim())
+                    .filter(|keyword| !keyword.is_empty())
+                    .map(|keyword| keyword.to_owned())
+                    .collect()
+            })
             .unwrap_or_default()
     }
 
+    pub fn select_language(&self, doc: &Html) -> Option<String> {
+        doc.select(&self.html_lang_selector)
+            .next()
+            .and_then(|html| html.attr("lang"))
+            .map(|lang| lang.trim().to_owned())
+            .filter(|lang| !lang.is_empty())
+    }
+
+    /// Content of the first `<meta>` whose `property` or `name` is `key`, as used by
+    /// OpenGraph (`og:*`) and Twitter card (`twitter:*`) tags.
+    pub fn select_meta(&self, doc: &Html, key: &str) -> Option<String> {
+        doc.select(&self.meta_selector)
+            .find(|meta| {
+                meta.attr("property")
+                    .or_else(|| meta.attr("name"))
+                    .is_some_and(|name| name.trim().eq_ignore_ascii_case(key))
+            })
+            .and_then(|meta| meta.attr("content"))
+            .map(|content| content.trim().to_owned())
+            .filter(|content| !content.is_empty())
+    }
+
+    pub fn select_meta_url(&self, doc: &Html, key: &str, page_url: &Url) -> Option<Url> {
+        self.select_meta(doc, key)
+            .and_then(|url| Self::normalize_url(&url, page_url).ok())
+    }
+
+    /// The page's OpenGraph tags, or `None` when it has none.
+    pub fn select_open_graph(&self, doc: &Html, page_url: &Url) -> Option<OpenGraph> {
+        let open_graph = OpenGraph {
+            title: self.select_meta(doc, "og:title"),
+            description: self.select_meta(doc, "og:description"),
+            image: self
+                .select_meta_url(doc, "og:image", page_url)
+                .map(|url| url.to_string()),
+            r#type: self.select_meta(doc, "og:type"),
+            site_name: self.select_meta(doc, "og:site_name"),
+            url: self
+                .select_meta_url(doc, "og:url", page_url)
+                .map(|url| url.to_string()),
+        };
+
+        (open_graph != Default::default()).then_some(open_graph)
+    }
+
+    /// The page's Twitter card tags, or `None` when it has none.
+    pub fn select_twitter_card(&self, doc: &Html, page_url: &Url) -> Option<TwitterCard> {
+        let twitter_card = TwitterCard {
+            card: self.select_meta(doc, "twitter:card"),
+            title: self.select_meta(doc, "twitter:title"),
+            description: self.select_meta(doc, "twitter:description"),
+            image: self
+                .select_meta_url(doc, "twitter:image", page_url)
+                .map(|url| url.to_string()),
+            site: self.select_meta(doc, "twitter:site"),
+            creator: self.select_meta(doc, "twitter:creator"),
+        };
+
+        (twitter_card != Default::default()).then_some(twitter_card)
+    }
+
+    /// Every `application/ld+json` block that parses as JSON, re-serialized compactly.
+    pub fn select_json_ld(&self, doc: &Html) -> Vec<String> {
+        doc.select(&self.json_ld_selector)
+            .filter_map(|script| {
+                serde_json::from_str::<serde_json::Value>(&script.text().collect::<String>()).ok()
+            })
+            .map(|json| json.to_string())
+            .collect()
+    }
+
     pub fn select_description(&self, doc: &Html) -> Option<String> {
         doc.select(&self.description_selector)
             .next()
             .and_then(|description| description.attr("content"))
-            .map(|description| description.to_owned())
+            .map(|description| description.trim().to_owned())
+            .filter(|description| !description.is_empty())
     }
 
     pub fn select_icon_url(&self, doc: &Html, page_url: &Url) -> Option<Url> {
@@ -139,6 +518,40 @@ impl SelectorSet {
             .collect()
     }
 
+    pub fn select_anchors(&self, doc: &Html, page_url: &Url) -> Vec<(Url, String)> {
+        doc.select(&self.anchor_selector)
+            .filter_map(|anchor| {
+                let mut url = Self::normalize_url(anchor.attr("href").unwrap(), page_url).ok()?;
+                if !matches!(url.scheme(), "http" | "https") {
+                    return None;
+                }
+                url.set_fragment(None);
+
+                let text = anchor
+                    .text()
+                    .flat_map(|text| text.split_whitespace())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+
+                let text = if text.is_empty() {
+                    anchor
+                        .attr("title")
+                        .or_else(|| {
+                            anchor
+                                .select(&self.images_selector)
+                                .find_map(|image| image.attr("alt"))
+                        })
+                        .map(|text| text.trim().to_owned())
+                        .unwrap_or_default()
+                } else {
+                    text
+                };
+
+                (!text.is_empty()).then_some((url, text))
+            })
+            .collect()
+    }
+
     pub fn select_urls(&self, doc: &Html, page_url: &Url) -> Vec<Url> {
         let href_tags = doc
             .select(&self.href_selector)
@@ -189,16 +602,16 @@ impl SelectorSet {
         let srcset_tags = doc
             .select(&self.srcset_selector)
             .map(|elem| elem.attr("srcset").unwrap())
-            .flat_map(|attr| attr.split(',').map(|csl| csl.split(' ').nth(1)))
-            .flatten();
+            .flat_map(parse_srcset)
+            .map(|candidate| candidate.url);
         let archive_tags = doc
             .select(&self.archive_selector)
             .map(|elem| elem.attr("archive").unwrap())
-            .flat_map(|attr| attr.split_whitespace().flat_map(|attr| attr.split(',')));
+            .flat_map(|attr| attr.split(|c: char| c == ',' || c.is_ascii_whitespace()));
         let meta_http_equiv_refresh_content_tags = doc
             .select(&self.meta_http_equiv_refresh_content_selector)
             .map(|elem| elem.attr("content").unwrap())
-            .filter_map(|attr| attr.split(';').nth(1));
+            .filter_map(refresh_url);
 
         let tags = href_tags
             .chain(codebase_tags)
@@ -218,7 +631,11 @@ impl SelectorSet {
             .chain(srcset_tags)
             .chain(archive_tags)
             .chain(meta_http_equiv_refresh_content_tags)
-            .filter_map(|url| Self::normalize_url(url, page_url).ok());
+            .map(|url| url.trim())
+            .filter(|url| !url.is_empty())
+            .filter_map(|url| Self::normalize_url(url, page_url).ok())
+            // `mailto:`, `javascript:` and inline `data:` URLs are nothing to crawl.
+            .filter(|url| matches!(url.scheme(), "http" | "https"));
 
         tags.collect()
     }
@@ -227,3 +644,192 @@ impl SelectorSet {
         Ok(base_url.join(url)?)
     }
 }
+
+/// The URL of a `<meta http-equiv="refresh">` content attribute such as `5; url='/next'`,
+/// following the HTML standard's "shared declarative refresh steps".
+fn refresh_url(content: &str) -> Option<&str> {
+    let rest = content.trim_start_matches(|c: char| c.is_ascii_whitespace());
+    if !rest.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+        return None;
+    }
+
+    let rest = rest
+        .trim_start_matches(|c: char| c.is_ascii_digit() || c == '.')
+        .trim_start_matches(|c: char| c.is_ascii_whitespace());
+    let rest = rest
+        .strip_prefix([';', ','])
+        .unwrap_or(rest)
+        .trim_start_matches(|c: char| c.is_ascii_whitespace());
+
+    // `url=` is optional, so a bare `5; /next` names a URL too.
+    let rest = rest
+        .get(..3)
+        .filter(|prefix| prefix.eq_ignore_ascii_case("url"))
+        .and_then(|_| {
+            rest[3..]
+                .trim_start_matches(|c: char| c.is_ascii_whitespace())
+                .strip_prefix('=')
+        })
+        .map_or(rest, |url| {
+            url.trim_start_matches(|c: char| c.is_ascii_whitespace())
+        });
+
+    let url = match rest.chars().next() {
+        Some(quote @ ('"' | '\'')) => {
+            let rest = &rest[1..];
+            &rest[..rest.find(quote).unwrap_or(rest.len())]
+        }
+        _ => rest,
+    };
+    let url = url.trim_end_matches(|c: char| c.is_ascii_whitespace());
+
+    (!url.is_empty()).then_some(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    /// Every fixture is extracted as if it had been fetched from here.
+    const PAGE_URL: &str = "https://example.com/blog/post.html";
+
+    fn extract(html: &str) -> Value {
+        let selector = SelectorSet::new();
+        let doc = Html::parse_document(html);
+        let page_url = Url::parse(PAGE_URL).unwrap();
+
+        let url = |url: Option<Url>| url.map(String::from);
+        let urls = |urls: Vec<Url>| urls.into_iter().map(String::from).collect::<Vec<_>>();
+
+        let images = selector
+            .select_images(&doc, &page_url)
+            .into_iter()
+            .map(|image| {
+                json!({
+                    "url": image.url.as_str(),
+                    "variants": image.variants.iter().map(|variant| json!({
+                        "url": variant.url.as_str(),
+                        "width": variant.width,
+                        "density": variant.density,
+                    })).collect::<Vec<_>>(),
+                    "alt_text": image.alt_text,
+                    "caption": image.context.caption,
+                    "title": image.context.title,
+                    "heading": image.context.heading,
+                    "paragraph": image.context.paragraph,
+                    "filename_tokens": image.context.filename_tokens,
+                    "width": image.width,
+                    "height": image.height,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let media = selector
+            .select_media(&doc, &page_url)
+            .into_iter()
+            .map(|media| {
+                json!({
+                    "kind": format!("{:?}", media.kind),
+                    "url": media.url.as_str(),
+                    "title": media.title,
+                    "mime_type": media.mime_type,
+                    "poster_url": url(media.poster_url),
+                    "width": media.width,
+                    "height": media.height,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        json!({
+            "title": selector.select_title(&doc),
+            "description": selector.select_description(&doc),
+            "keywords": selector.select_keywords(&doc),
+            "language": selector.select_language(&doc),
+            "icon_url": url(selector.select_icon_url(&doc, &page_url)),
+            "manifest_url": url(selector.select_manifest_url(&doc, &page_url)),
+            "feed_urls": urls(selector.select_feed_urls(&doc, &page_url)),
+            "open_graph": selector.select_open_graph(&doc, &page_url),
+            "twitter_card": selector.select_twitter_card(&doc, &page_url),
+            "json_ld": selector.select_json_ld(&doc),
+            "sections": selector.select_sections(&doc),
+            "text_fields": selector.select_text_fields(&doc),
+            "anchors": selector
+                .select_anchors(&doc, &page_url)
+                .into_iter()
+                .map(|(url, text)| json!([url.as_str(), text]))
+                .collect::<Vec<_>>(),
+            "urls": urls(selector.select_urls(&doc, &page_url)),
+            "images": images,
+            "media": media,
+        })
+    }
+
+    /// Extracts every `tests/fixtures/selector_set/*.html` and compares the result with
+    /// the `.json` file of the same name. After an intended change in extraction, run
+    /// with `UPDATE_GOLDEN=1` to rewrite the expectations, then review their diff.
+    #[test]
+    fn matches_golden_files() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/selector_set");
+        let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+
+        let mut fixtures = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "html"))
+            .collect::<Vec<_>>();
+        fixtures.sort();
+        assert!(!fixtures.is_empty(), "no fixtures in {}", dir.display());
+
+        let mut mismatched = Vec::new();
+        for fixture in fixtures {
+            let actual = extract(&fs::read_to_string(&fixture).unwrap());
+            let golden = fixture.with_extension("json");
+
+            if update {
+                let json = serde_json::to_string_pretty(&actual).unwrap();
+                fs::write(&golden, json + "\n").unwrap();
+                continue;
+            }
+
+            let expected: Value = serde_json::from_str(
+                &fs::read_to_string(&golden)
+                    .unwrap_or_else(|err| panic!("reading {}: {}", golden.display(), err)),
+            )
+            .unwrap();
+
+            if actual != expected {
+                eprintln!(
+                    "{} differs from {}:\n{}",
+                    fixture.display(),
+                    golden.display(),
+                    serde_json::to_string_pretty(&actual).unwrap()
+                );
+                mismatched.push(fixture);
+            }
+        }
+
+        assert!(
+            mismatched.is_empty(),
+            "mismatched fixtures: {:?}",
+            mismatched
+        );
+    }
+
+    #[test]
+    fn parses_refresh_urls() {
+        assert_eq!(refresh_url("5; url=/next"), Some("/next"));
+        assert_eq!(
+            refresh_url("0;URL='https://example.org/a b'"),
+            Some("https://example.org/a b")
+        );
+        assert_eq!(refresh_url("3 , URL = \"/quoted\" "), Some("/quoted"));
+        assert_eq!(refresh_url("1; /bare"), Some("/bare"));
+        assert_eq!(refresh_url("1; urlish"), Some("urlish"));
+        assert_eq!(refresh_url("10"), None);
+        assert_eq!(refresh_url("url=/no-delay"), None);
+    }
+}
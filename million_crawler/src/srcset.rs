@@ -0,0 +1,163 @@
+/// Size given for one `srcset` candidate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Descriptor {
+    /// No descriptor, which means `1x`.
+    None,
+    /// `w` descriptor: the image's intrinsic width in pixels.
+    Width(u32),
+    /// `x` descriptor: pixel density.
+    Density(f32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Candidate<'a> {
+    pub url: &'a str,
+    pub descriptor: Descriptor,
+}
+
+/// Splits a `srcset` attribute into its candidates, following the HTML parsing rules:
+/// URLs may contain commas, and descriptors run to the next comma outside parentheses.
+pub fn parse_srcset(srcset: &str) -> Vec<Candidate<'_>> {
+    let mut candidates = Vec::new();
+    let mut rest = srcset;
+
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace() || c == ',');
+        if rest.is_empty() {
+            break;
+        }
+
+        let url_end = rest
+            .find(|c: char| c.is_ascii_whitespace())
+            .unwrap_or(rest.len());
+        let (url, after_url) = rest.split_at(url_end);
+        rest = after_url;
+
+        let descriptors = if url.ends_with(',') {
+            ""
+        } else {
+            let mut depth = 0usize;
+            let end = rest
+                .char_indices()
+                .find(|(_, c)| match c {
+                    '(' => {
+                        depth += 1;
+                        false
+                    }
+                    ')' => {
+                        depth = depth.saturating_sub(1);
+                        false
+                    }
+                    ',' => depth == 0,
+                    _ => false,
+                })
+                .map_or(rest.len(), |(i, _)| i);
+
+            let (descriptors, after_descriptors) = rest.split_at(end);
+            rest = after_descriptors;
+            descriptors
+        };
+
+        let url = url.trim_end_matches(',');
+        if url.is_empty() {
+            continue;
+        }
+
+        candidates.push(Candidate {
+            url,
+            descriptor: parse_descriptor(descriptors),
+        });
+    }
+
+    candidates
+}
+
+fn parse_descriptor(descriptors: &str) -> Descriptor {
+    descriptors
+        .split_ascii_whitespace()
+        .find_map(|descriptor| {
+            if let Some(width) = descriptor.strip_suffix('w') {
+                width.parse().ok().map(Descriptor::Width)
+            } else if let Some(density) = descriptor.strip_suffix('x') {
+                density.parse().ok().map(Descriptor::Density)
+            } else {
+                None
+            }
+        })
+        .unwrap_or(Descriptor::None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(url: &str, descriptor: Descriptor) -> Candidate<'_> {
+        Candidate { url, descriptor }
+    }
+
+    #[test]
+    fn reads_width_density_and_missing_descriptors() {
+        assert_eq!(
+            parse_srcset("small.jpg 480w, large.jpg 1080w"),
+            [
+                candidate("small.jpg", Descriptor::Width(480)),
+                candidate("large.jpg", Descriptor::Width(1080)),
+            ]
+        );
+        assert_eq!(
+            parse_srcset("a.png, a@2x.png 2x,a@1.5x.png 1.5x"),
+            [
+                candidate("a.png", Descriptor::None),
+                candidate("a@2x.png", Descriptor::Density(2.0)),
+                candidate("a@1.5x.png", Descriptor::Density(1.5)),
+            ]
+        );
+        // Descriptors that do not parse fall back to `1x`.
+        assert_eq!(
+            parse_srcset("a.png wide"),
+            [candidate("a.png", Descriptor::None)]
+        );
+    }
+
+    #[test]
+    fn keeps_commas_inside_urls() {
+        assert_eq!(
+            parse_srcset("/img/w_400,h_300/a.jpg 400w, /img/w_800,h_600/a.jpg 800w"),
+            [
+                candidate("/img/w_400,h_300/a.jpg", Descriptor::Width(400)),
+                candidate("/img/w_800,h_600/a.jpg", Descriptor::Width(800)),
+            ]
+        );
+        assert_eq!(
+            parse_srcset("data:image/png;base64,iVBORw0KGgo= 1x"),
+            [candidate(
+                "data:image/png;base64,iVBORw0KGgo=",
+                Descriptor::Density(1.0)
+            )]
+        );
+    }
+
+    #[test]
+    fn skips_commas_inside_parentheses() {
+        assert_eq!(
+            parse_srcset("a.jpg 100w (future, syntax), b.jpg 200w"),
+            [
+                candidate("a.jpg", Descriptor::Width(100)),
+                candidate("b.jpg", Descriptor::Width(200)),
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_empty_candidates_and_trailing_commas() {
+        assert_eq!(parse_srcset(""), []);
+        assert_eq!(parse_srcset(" , ,"), []);
+        assert_eq!(
+            parse_srcset(" a.jpg 1x,, b.jpg 2x ,"),
+            [
+                candidate("a.jpg", Descriptor::Density(1.0)),
+                candidate("b.jpg", Descriptor::Density(2.0)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,342 @@
+use pulldown_cmark::{Event, HeadingLevel, Parser, Tag, TagEnd};
+use url::Url;
+
+/// Longest title taken from the first line of a document without real metadata.
+const MAX_TITLE_LENGTH: usize = 200;
+
+/// XML elements whose text is treated as a title or section heading.
+const XML_HEADING_TAGS: &[&str] = &["title", "heading", "h1", "h2", "h3", "h4", "h5", "h6"];
+
+#[derive(Debug, Default)]
+pub struct TextDocument {
+    pub title: Option<String>,
+    pub sections: Vec<String>,
+    pub text_fields: Vec<String>,
+    pub linked_urls: Vec<Url>,
+    pub anchors: Vec<(Url, String)>,
+}
+
+/// Splits a `text/plain` body into paragraphs on blank lines, using the first line as the
+/// title and collecting bare `http(s)://` links.
+pub fn extract_plain_text(text: &str, page_url: &Url) -> TextDocument {
+    let text_fields = text
+        .split("\n\n")
+        .flat_map(|paragraph| paragraph.split("\r\n\r\n"))
+        .map(collapse_whitespace)
+        .filter(|paragraph| !paragraph.is_empty())
+        .collect::<Vec<_>>();
+
+    TextDocument {
+        title: first_line_title(text),
+        sections: vec![],
+        linked_urls: bare_urls(text, page_url),
+        anchors: vec![],
+        text_fields,
+    }
+}
+
+/// Walks a Markdown document, turning headings into `sections`, blocks into `text_fields`
+/// and links into linked URLs with their anchor text.
+pub fn extract_markdown(text: &str, page_url: &Url) -> TextDocument {
+    let mut document = TextDocument::default();
+
+    let mut first_heading = None;
+    let mut buffer = String::new();
+    let mut links: Vec<(String, String)> = Vec::new();
+
+    for event in Parser::new(text) {
+        match event {
+            Event::Start(Tag::Heading { .. }) => {
+                flush(&mut buffer, &mut document.text_fields);
+            }
+            Event::End(TagEnd::Heading(level)) => {
+                let heading = collapse_whitespace(&buffer);
+                buffer.clear();
+
+                if heading.is_empty() {
+                    continue;
+                }
+
+                if level == HeadingLevel::H1 && document.title.is_none() {
+                    document.title = Some(heading.clone());
+                }
+                first_heading.get_or_insert_with(|| heading.clone());
+                document.sections.push(heading);
+            }
+            Event::End(
+                TagEnd::Paragraph
+                | TagEnd::Item
+                | TagEnd::CodeBlock
+                | TagEnd::BlockQuote
+                | TagEnd::TableRow
+                | TagEnd::TableHead,
+            ) => {
+                flush(&mut buffer, &mut document.text_fields);
+            }
+            Event::Start(Tag::Link { dest_url, .. }) => {
+                links.push((dest_url.into_string(), String::new()));
+            }
+            Event::End(TagEnd::Link) => {
+                let Some((dest_url, text)) = links.pop() else {
+                    continue;
+                };
+                let Ok(mut url) = page_url.join(&dest_url) else {
+                    continue;
+                };
+                url.set_fragment(None);
+
+                let text = collapse_whitespace(&text);
+                if !text.is_empty() {
+                    document.anchors.push((url.clone(), text));
+                }
+                document.linked_urls.push(url);
+            }
+            Event::Text(text) | Event::Code(text) => {
+                buffer.push_str(&text);
+                for (_, link_text) in links.iter_mut() {
+                    link_text.push_str(&text);
+                }
+            }
+            Event::SoftBreak | Event::HardBreak => {
+                buffer.push(' ');
+            }
+            Event::End(TagEnd::TableCell) => {
+                buffer.push(' ');
+            }
+            _ => {}
+        }
+    }
+
+    flush(&mut buffer, &mut document.text_fields);
+
+    document.title = document
+        .title
+        .or(first_heading)
+        .or_else(|| first_line_title(text));
+
+    document
+}
+
+/// Extracts text from a generic XML document. The first `<title>` element becomes the
+/// title, other `title`/`heading` elements become sections, and sitemap `<loc>` and
+/// `xlink:href` values are returned as linked URLs.
+pub fn extract_xml(text: &str, page_url: &Url) -> anyhow::Result<TextDocument> {
+    // Crawled XML is untrusted, and a DTD's entities can expand into gigabytes of text.
+    let xml = roxmltree::Document::parse_with_options(
+        text,
+        roxmltree::ParsingOptions {
+            allow_dtd: false,
+            ..Default::default()
+        },
+    )?;
+
+    let mut document = TextDocument::default();
+
+    for node in xml.descendants() {
+        if node.is_element() {
+            let name = node.tag_name().name().to_ascii_lowercase();
+
+            if XML_HEADING_TAGS.contains(&name.as_str()) || name == "loc" {
+                let text = collapse_whitespace(
+                    &node
+                        .descendants()
+                        .filter(|node| node.is_text())
+                        .filter_map(|node| node.text())
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                );
+
+                if name == "loc" {
+                    if let Ok(url) = page_url.join(&text) {
+                        document.linked_urls.push(url);
+                    }
+                } else if text.is_empty() {
+                    continue;
+                } else if name == "title" && document.title.is_none() {
+                    document.title = Some(text);
+                } else {
+                    document.sections.push(text);
+                }
+            }
+
+            if let Some(href) = node
+                .attributes()
+                .find(|attr| attr.name() == "href")
+                .map(|attr| attr.value())
+            {
+                if let Ok(url) = page_url.join(href) {
+                    document.linked_urls.push(url);
+                }
+            }
+        } else if let Some(text) = node.text().filter(|_| node.is_text()) {
+            let parent = node
+                .parent_element()
+                .map(|parent| parent.tag_name().name().to_ascii_lowercase())
+                .unwrap_or_default();
+
+            if XML_HEADING_TAGS.contains(&parent.as_str()) || parent == "loc" {
+                continue;
+            }
+
+            let text = collapse_whitespace(text);
+            if !text.is_empty() {
+                document.text_fields.push(text);
+            }
+        }
+    }
+
+    Ok(document)
+}
+
+fn flush(buffer: &mut String, text_fields: &mut Vec<String>) {
+    let text = collapse_whitespace(buffer);
+    if !text.is_empty() {
+        text_fields.push(text);
+    }
+    buffer.clear();
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn first_line_title(text: &str) -> Option<String> {
+    text.lines()
+        .map(|line| line.trim().trim_start_matches('#').trim())
+        .find(|line| !line.is_empty())
+        .map(|line| line.chars().take(MAX_TITLE_LENGTH).collect())
+}
+
+fn bare_urls(text: &str, page_url: &Url) -> Vec<Url> {
+    text.split_whitespace()
+        .map(|word| word.trim_start_matches(['(', '[', '<', '"', '\'']))
+        .filter(|word| word.starts_with("http://") || word.starts_with("https://"))
+        .map(|word| word.trim_end_matches(['.', ',', ';', ':', ')', ']', '>', '"', '\'']))
+        .filter_map(|word| page_url.join(word).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page_url() -> Url {
+        Url::parse("https://example.com/docs/readme.txt").unwrap()
+    }
+
+    fn urls(urls: &[Url]) -> Vec<&str> {
+        urls.iter().map(Url::as_str).collect()
+    }
+
+    #[test]
+    fn splits_plain_text_into_paragraphs() {
+        let text = "  Release notes\n\nFixed a crash.\nSee https://example.org/bug/1.\r\n\r\n\n\nThanks (https://example.org/thanks)";
+        let document = extract_plain_text(text, &page_url());
+
+        assert_eq!(document.title.as_deref(), Some("Release notes"));
+        assert_eq!(
+            document.text_fields,
+            [
+                "Release notes",
+                "Fixed a crash. See https://example.org/bug/1.",
+                "Thanks (https://example.org/thanks)",
+            ]
+        );
+        assert_eq!(
+            urls(&document.linked_urls),
+            ["https://example.org/bug/1", "https://example.org/thanks"]
+        );
+    }
+
+    #[test]
+    fn reads_markdown_headings_blocks_and_links() {
+        let text = "\
+Intro without a heading.
+
+## Install
+
+Run `cargo install` or see [the *guide*](guide.md#setup).
+
+# Project
+
+- one
+- [two](https://example.org/two)
+";
+        let document = extract_markdown(text, &page_url());
+
+        assert_eq!(document.title.as_deref(), Some("Project"));
+        assert_eq!(document.sections, ["Install", "Project"]);
+        assert_eq!(
+            document.text_fields,
+            [
+                "Intro without a heading.",
+                "Run cargo install or see the guide.",
+                "one",
+                "two",
+            ]
+        );
+        assert_eq!(
+            urls(&document.linked_urls),
+            [
+                "https://example.com/docs/guide.md",
+                "https://example.org/two"
+            ]
+        );
+        assert_eq!(
+            document.anchors,
+            [
+                (
+                    Url::parse("https://example.com/docs/guide.md").unwrap(),
+                    String::from("the guide")
+                ),
+                (
+                    Url::parse("https://example.org/two").unwrap(),
+                    String::from("two")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn falls_back_to_first_markdown_heading_or_line() {
+        let document = extract_markdown("### Notes\n\nbody", &page_url());
+        assert_eq!(document.title.as_deref(), Some("Notes"));
+
+        let document = extract_markdown("just a line\n\nand more", &page_url());
+        assert_eq!(document.title.as_deref(), Some("just a line"));
+    }
+
+    #[test]
+    fn reads_xml_titles_sections_and_links() {
+        let text = r#"<?xml version="1.0"?>
+<book xmlns:xlink="http://www.w3.org/1999/xlink">
+  <title>Manual</title>
+  <chapter>
+    <title>Getting started</title>
+    <para>Read <link xlink:href="/start">this</link> first.</para>
+  </chapter>
+  <loc> sitemap.xml </loc>
+</book>"#;
+        let document = extract_xml(text, &page_url()).unwrap();
+
+        assert_eq!(document.title.as_deref(), Some("Manual"));
+        assert_eq!(document.sections, ["Getting started"]);
+        assert_eq!(document.text_fields, ["Read", "this", "first."]);
+        assert_eq!(
+            urls(&document.linked_urls),
+            [
+                "https://example.com/start",
+                "https://example.com/docs/sitemap.xml"
+            ]
+        );
+    }
+
+    #[test]
+    fn refuses_xml_with_a_dtd() {
+        let text = r#"<?xml version="1.0"?>
+<!DOCTYPE lolz [<!ENTITY lol "lol"><!ENTITY lol2 "&lol;&lol;&lol;&lol;">]>
+<lolz>&lol2;</lolz>"#;
+
+        assert!(extract_xml(text, &page_url()).is_err());
+    }
+}
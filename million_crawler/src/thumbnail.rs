@@ -0,0 +1,26 @@
+use image::{codecs::webp::WebPEncoder, DynamicImage, ExtendedColorType};
+
+/// Longest side of a generated thumbnail in pixels.
+const THUMBNAIL_SIZE: u32 = 256;
+
+/// Encodes a WebP thumbnail that fits in [`THUMBNAIL_SIZE`], never upscaling.
+pub fn webp_thumbnail(img: &DynamicImage) -> Option<Vec<u8>> {
+    let thumbnail = if img.width() > THUMBNAIL_SIZE || img.height() > THUMBNAIL_SIZE {
+        img.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+    } else {
+        img.clone()
+    }
+    .into_rgba8();
+
+    let mut bytes = Vec::new();
+    WebPEncoder::new_lossless(&mut bytes)
+        .encode(
+            &thumbnail,
+            thumbnail.width(),
+            thumbnail.height(),
+            ExtendedColorType::Rgba8,
+        )
+        .ok()?;
+
+    Some(bytes)
+}
@@ -0,0 +1,578 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
+use chrono::{SecondsFormat, Utc};
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue, CONTENT_LENGTH, HOST, TRANSFER_ENCODING},
+    ResponseBuilderExt, StatusCode, Version,
+};
+use tokio::{fs::File, io::AsyncWriteExt, sync::Mutex};
+use url::Url;
+use uuid::Uuid;
+
+/// Marks the request record of a job's page, so a replay knows which pages to extract.
+const JOB_FIELD: &str = "Million-Crawl-Job";
+
+/// Where and how the crawler archives what it fetches.
+#[derive(clap::Args, Debug, Clone)]
+pub struct WarcOptions {
+    /// Directory to write WARC files of every request and response to. `replay` reads
+    /// each file whole into memory, so keep `--warc-max-file-bytes` well below that.
+    #[arg(long, env)]
+    pub warc_dir: Option<PathBuf>,
+
+    /// Size after which a new WARC file is started, in bytes.
+    #[arg(long, env, default_value_t = 1024 * 1024 * 1024)]
+    pub warc_max_file_bytes: u64,
+}
+
+/// Appends request/response record pairs to a series of WARC files, starting a new file
+/// once the current one passes `max_file_bytes`.
+#[derive(Debug)]
+pub struct WarcWriter {
+    dir: PathBuf,
+    max_file_bytes: u64,
+    state: Mutex<WriterState>,
+}
+
+#[derive(Debug, Default)]
+struct WriterState {
+    file: Option<File>,
+    written: u64,
+    sequence: u32,
+}
+
+impl WarcWriter {
+    pub async fn new(options: &WarcOptions) -> anyhow::Result<Option<Self>> {
+        let Some(dir) = &options.warc_dir else {
+            return Ok(None);
+        };
+
+        tokio::fs::create_dir_all(dir).await?;
+
+        Ok(Some(Self {
+            dir: dir.clone(),
+            max_file_bytes: options.warc_max_file_bytes,
+            state: Mutex::new(WriterState::default()),
+        }))
+    }
+
+    /// Records one exchange. reqwest has already removed any transfer encoding, so the
+    /// HTTP blocks are reassembled from the parsed request and response. WARC stores
+    /// HTTP/1 messages, so an HTTP/2 or HTTP/3 response is written as HTTP/1.1.
+    pub async fn write_exchange(
+        &self,
+        url: &Url,
+        request_headers: &HeaderMap,
+        is_job: bool,
+        response: &StoredResponse,
+    ) -> anyhow::Result<()> {
+        let response_id = record_id();
+
+        let mut request_block = format!("GET {} HTTP/1.1\r\n", request_target(url)).into_bytes();
+        if let Some(host) = url.host_str() {
+            let host = match url.port() {
+                Some(port) => format!("{}:{}", host, port),
+                None => host.to_owned(),
+            };
+            write_header(&mut request_block, HOST.as_str(), host.as_bytes());
+        }
+        for (name, value) in request_headers {
+            write_header(&mut request_block, name.as_str(), value.as_bytes());
+        }
+        request_block.extend_from_slice(b"\r\n");
+
+        let version = match response.version {
+            Version::HTTP_10 => "HTTP/1.0",
+            _ => "HTTP/1.1",
+        };
+        let mut response_block = format!(
+            "{} {} {}\r\n",
+            version,
+            response.status.as_u16(),
+            response.status.canonical_reason().unwrap_or_default()
+        )
+        .into_bytes();
+        for (name, value) in &response.headers {
+            if name != TRANSFER_ENCODING && name != CONTENT_LENGTH {
+                write_header(&mut response_block, name.as_str(), value.as_bytes());
+            }
+        }
+        write_header(
+            &mut response_block,
+            CONTENT_LENGTH.as_str(),
+            response.body.len().to_string().as_bytes(),
+        );
+        response_block.extend_from_slice(b"\r\n");
+        response_block.extend_from_slice(&response.body);
+
+        let mut request_fields = vec![("WARC-Concurrent-To", response_id.clone())];
+        if is_job {
+            request_fields.push((JOB_FIELD, String::from("true")));
+        }
+
+        let mut records = record(
+            "response",
+            &response_id,
+            Some(url),
+            "application/http;msgtype=response",
+            &[],
+            &response_block,
+        );
+        records.extend(record(
+            "request",
+            &record_id(),
+            Some(url),
+            "application/http;msgtype=request",
+            &request_fields,
+            &request_block,
+        ));
+
+        self.append(&records).await
+    }
+
+    async fn append(&self, records: &[u8]) -> anyhow::Result<()> {
+        let mut state = self.state.lock().await;
+
+        if state.file.is_none() || state.written >= self.max_file_bytes {
+            state.sequence += 1;
+            let name = format!(
+                "million-{}-{}-{:05}.warc",
+                Utc::now().format("%Y%m%d%H%M%S"),
+                std::process::id(),
+                state.sequence
+            );
+
+            let info = format!(
+                "software: million_crawler/{}\r\nformat: WARC File Format 1.1\r\n",
+                env!("CARGO_PKG_VERSION")
+            );
+            let info = record(
+                "warcinfo",
+                &record_id(),
+                None,
+                "application/warc-fields",
+                &[("WARC-Filename", name.clone())],
+                info.as_bytes(),
+            );
+
+            let mut file = File::create(self.dir.join(&name)).await?;
+            file.write_all(&info).await?;
+
+            state.written = info.len() as u64;
+            state.file = Some(file);
+        }
+
+        let file = state.file.as_mut().expect("a WARC file was just opened");
+        file.write_all(records).await?;
+        file.flush().await?;
+        state.written += records.len() as u64;
+
+        Ok(())
+    }
+}
+
+/// A response as written to, or read back from, a WARC file.
+#[derive(Debug, Clone)]
+pub struct StoredResponse {
+    pub version: Version,
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+impl StoredResponse {
+    pub fn into_response(self, url: Url) -> anyhow::Result<reqwest::Response> {
+        let mut builder = http::Response::builder()
+            .status(self.status)
+            .version(self.version)
+            .url(url);
+        if let Some(headers) = builder.headers_mut() {
+            *headers = self.headers;
+        }
+
+        Ok(builder.body(self.body)?.into())
+    }
+}
+
+/// Responses from earlier crawls, looked up by URL instead of going to the network.
+#[derive(Debug, Default)]
+pub struct WarcReplay {
+    responses: HashMap<String, StoredResponse>,
+    jobs: Vec<Url>,
+}
+
+impl WarcReplay {
+    /// Reads a WARC file, or every `.warc` file in a directory. When a URL was fetched
+    /// more than once, the latest response wins. Records that can't be read are skipped.
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let mut paths = if path.is_dir() {
+            std::fs::read_dir(path)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .filter(|path| {
+                    path.as_ref().map_or(true, |path| {
+                        path.extension().is_some_and(|ext| ext == "warc")
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?
+        } else {
+            vec![path.to_owned()]
+        };
+        paths.sort();
+
+        let mut replay = Self::default();
+        for path in paths {
+            let bytes = std::fs::read(&path)?;
+            replay.add_records(&path, &bytes);
+        }
+
+        Ok(replay)
+    }
+
+    /// Adds a response for `url`, replacing any earlier one.
+    pub fn insert(&mut self, url: &Url, response: StoredResponse) {
+        self.responses.insert(url.to_string(), response);
+    }
+
+    /// Pages that were crawled as jobs, in the order they were first fetched.
+    pub fn jobs(&self) -> &[Url] {
+        &self.jobs
+    }
+
+    pub fn response(&self, url: &Url) -> anyhow::Result<reqwest::Response> {
+        self.responses
+            .get(url.as_str())
+            .cloned()
+            .ok_or_else(|| anyhow!("{} is not in the archive", url))?
+            .into_response(url.clone())
+    }
+
+    fn add_records(&mut self, path: &Path, mut bytes: &[u8]) {
+        while !bytes.is_empty() {
+            match split_record(bytes) {
+                Ok((record, rest)) => {
+                    if let Err(err) = self.add_record(&record) {
+                        tracing::warn!("skipping record in {}: {}", path.display(), err);
+                    }
+                    bytes = rest;
+                }
+                Err(err) => {
+                    tracing::warn!("skipping record in {}: {}", path.display(), err);
+
+                    // The record's length can't be trusted, so resume at the next header.
+                    match find(&bytes[1..], b"\r\nWARC/") {
+                        Some(position) => bytes = &bytes[position + 3..],
+                        None => break,
+                    }
+                }
+            }
+        }
+    }
+
+    fn add_record(&mut self, record: &Record<'_>) -> anyhow::Result<()> {
+        let fields = &record.fields;
+        let Some(target) = fields.get("warc-target-uri") else {
+            return Ok(());
+        };
+
+        match fields.get("warc-type").copied() {
+            Some("response") => {
+                self.responses
+                    .insert(target.to_string(), parse_response(record.block)?);
+            }
+            Some("request") if fields.contains_key(&JOB_FIELD.to_ascii_lowercase()) => {
+                let url: Url = target.parse()?;
+                if !self.jobs.contains(&url) {
+                    self.jobs.push(url);
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+/// A WARC record's header fields, keyed in lowercase, and its block.
+struct Record<'a> {
+    fields: HashMap<String, &'a str>,
+    block: &'a [u8],
+}
+
+/// Splits the first record off `bytes`, returning it and the bytes after it.
+fn split_record(bytes: &[u8]) -> anyhow::Result<(Record<'_>, &[u8])> {
+    let header_end = find(bytes, b"\r\n\r\n").ok_or_else(|| anyhow!("truncated record"))?;
+    let header = std::str::from_utf8(&bytes[..header_end])?;
+
+    let mut lines = header.split("\r\n");
+    if !lines.next().is_some_and(|line| line.starts_with("WARC/")) {
+        return Err(anyhow!("not a WARC record"));
+    }
+    let fields = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim()))
+        .collect::<HashMap<_, _>>();
+
+    let length: usize = fields
+        .get("content-length")
+        .ok_or_else(|| anyhow!("record without Content-Length"))?
+        .parse()?;
+    let block_start = header_end + 4;
+    let block = bytes
+        .get(block_start..block_start + length)
+        .ok_or_else(|| anyhow!("truncated record"))?;
+    let rest = bytes.get(block_start + length + 4..).unwrap_or_default();
+
+    Ok((Record { fields, block }, rest))
+}
+
+fn parse_response(block: &[u8]) -> anyhow::Result<StoredResponse> {
+    let mut headers = [httparse::EMPTY_HEADER; 128];
+    let mut parsed = httparse::Response::new(&mut headers);
+
+    let httparse::Status::Complete(body_start) = parsed.parse(block)? else {
+        return Err(anyhow!("truncated HTTP response"));
+    };
+
+    let mut header_map = HeaderMap::new();
+    for header in parsed.headers.iter() {
+        header_map.append(
+            HeaderName::from_bytes(header.name.as_bytes())?,
+            HeaderValue::from_bytes(header.value)?,
+        );
+    }
+
+    Ok(StoredResponse {
+        version: match parsed.version {
+            Some(0) => Version::HTTP_10,
+            _ => Version::HTTP_11,
+        },
+        status: StatusCode::from_u16(parsed.code.unwrap_or(200))?,
+        headers: header_map,
+        body: block[body_start..].to_vec(),
+    })
+}
+
+fn record(
+    kind: &str,
+    id: &str,
+    target: Option<&Url>,
+    content_type: &str,
+    fields: &[(&str, String)],
+    block: &[u8],
+) -> Vec<u8> {
+    let mut record = b"WARC/1.1\r\n".to_vec();
+    write_header(&mut record, "WARC-Type", kind.as_bytes());
+    write_header(&mut record, "WARC-Record-ID", id.as_bytes());
+    write_header(
+        &mut record,
+        "WARC-Date",
+        Utc::now()
+            .to_rfc3339_opts(SecondsFormat::Secs, true)
+            .as_bytes(),
+    );
+    if let Some(target) = target {
+        write_header(&mut record, "WARC-Target-URI", target.as_str().as_bytes());
+    }
+    for (name, value) in fields {
+        write_header(&mut record, name, value.as_bytes());
+    }
+    write_header(&mut record, "Content-Type", content_type.as_bytes());
+    write_header(
+        &mut record,
+        "Content-Length",
+        block.len().to_string().as_bytes(),
+    );
+    record.extend_from_slice(b"\r\n");
+    record.extend_from_slice(block);
+    record.extend_from_slice(b"\r\n\r\n");
+    record
+}
+
+fn record_id() -> String {
+    format!("<urn:uuid:{}>", Uuid::new_v4())
+}
+
+fn request_target(url: &Url) -> String {
+    match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_owned(),
+    }
+}
+
+fn write_header(out: &mut Vec<u8>, name: &str, value: &[u8]) {
+    out.extend_from_slice(name.as_bytes());
+    out.extend_from_slice(b": ");
+    out.extend_from_slice(value);
+    out.extend_from_slice(b"\r\n");
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::{CONTENT_TYPE, USER_AGENT};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn replays_written_exchanges() {
+        let dir = std::env::temp_dir().join(format!("million-warc-{}", Uuid::new_v4()));
+        let writer = WarcWriter::new(&WarcOptions {
+            warc_dir: Some(dir.clone()),
+            warc_max_file_bytes: 1024 * 1024,
+        })
+        .await
+        .unwrap()
+        .unwrap();
+
+        let page_url = Url::parse("https://example.com:8443/page?q=1").unwrap();
+        let image_url = Url::parse("https://example.com/missing.png").unwrap();
+        let h2_url = Url::parse("https://example.com/h2").unwrap();
+
+        let mut request_headers = HeaderMap::new();
+        request_headers.insert(USER_AGENT, HeaderValue::from_static("MillionSearchBot"));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/html"));
+        headers.insert(TRANSFER_ENCODING, HeaderValue::from_static("chunked"));
+        // A blank line inside the body must not end the record early.
+        let body = b"<p>one</p>\r\n\r\n<p>two</p>".to_vec();
+
+        writer
+            .write_exchange(
+                &page_url,
+                &request_headers,
+                true,
+                &StoredResponse {
+                    version: Version::HTTP_11,
+                    status: StatusCode::OK,
+                    headers,
+                    body: body.clone(),
+                },
+            )
+            .await
+            .unwrap();
+        writer
+            .write_exchange(
+                &image_url,
+                &request_headers,
+                false,
+                &StoredResponse {
+                    version: Version::HTTP_11,
+                    status: StatusCode::NOT_FOUND,
+                    headers: HeaderMap::new(),
+                    body: vec![],
+                },
+            )
+            .await
+            .unwrap();
+        writer
+            .write_exchange(
+                &h2_url,
+                &request_headers,
+                false,
+                &StoredResponse {
+                    version: Version::HTTP_2,
+                    status: StatusCode::OK,
+                    headers: HeaderMap::new(),
+                    body: b"over h2".to_vec(),
+                },
+            )
+            .await
+            .unwrap();
+
+        let replay = WarcReplay::open(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+        let replay = replay.unwrap();
+
+        assert_eq!(replay.jobs(), std::slice::from_ref(&page_url));
+
+        let page = replay.response(&page_url).unwrap();
+        assert_eq!(page.status(), StatusCode::OK);
+        assert_eq!(page.headers()[CONTENT_TYPE], "text/html");
+        assert_eq!(
+            page.headers()[CONTENT_LENGTH],
+            body.len().to_string().as_str()
+        );
+        assert!(page.headers().get(TRANSFER_ENCODING).is_none());
+        assert_eq!(page.bytes().await.unwrap(), body);
+
+        let image = replay.response(&image_url).unwrap();
+        assert_eq!(image.status(), StatusCode::NOT_FOUND);
+        assert!(image.bytes().await.unwrap().is_empty());
+
+        let h2 = replay.response(&h2_url).unwrap();
+        assert_eq!(h2.status(), StatusCode::OK);
+        assert_eq!(h2.version(), Version::HTTP_11);
+        assert_eq!(h2.bytes().await.unwrap(), &b"over h2"[..]);
+
+        assert!(replay
+            .response(&Url::parse("https://example.com/other").unwrap())
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn skips_unreadable_records() {
+        let first = Url::parse("https://example.com/first").unwrap();
+        let bad = Url::parse("https://example.com/bad").unwrap();
+        let last = Url::parse("https://example.com/last").unwrap();
+        let response = |body: &str| {
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            )
+        };
+
+        let mut bytes = record(
+            "response",
+            &record_id(),
+            Some(&first),
+            "application/http;msgtype=response",
+            &[],
+            response("first").as_bytes(),
+        );
+        // An HTTP block that doesn't parse.
+        bytes.extend(record(
+            "response",
+            &record_id(),
+            Some(&bad),
+            "application/http;msgtype=response",
+            &[],
+            b"not http\r\n\r\n",
+        ));
+        // A record whose Content-Length runs past its block.
+        bytes.extend_from_slice(
+            b"WARC/1.1\r\nWARC-Type: response\r\nContent-Length: 9999\r\n\r\nshort\r\n\r\n",
+        );
+        bytes.extend(record(
+            "response",
+            &record_id(),
+            Some(&last),
+            "application/http;msgtype=response",
+            &[],
+            response("last").as_bytes(),
+        ));
+
+        let mut replay = WarcReplay::default();
+        replay.add_records(Path::new("test.warc"), &bytes);
+
+        assert_eq!(
+            replay.response(&first).unwrap().bytes().await.unwrap(),
+            &b"first"[..]
+        );
+        assert!(replay.response(&bad).is_err());
+        assert_eq!(
+            replay.response(&last).unwrap().bytes().await.unwrap(),
+            &b"last"[..]
+        );
+    }
+}
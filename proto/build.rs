@@ -1,10 +1,33 @@
 fn main() -> Result<(), Box<dyn std::error::Error>> {
 
-    tonic_build::configure().protoc_arg("--experimental_allow_proto3_optional").compile(&[
-        "proto/search.proto",
-        "proto/admin.proto",
-        "proto/crawler.proto",
-    ], &["proto"])?;
+    tonic_build::configure()
+        .protoc_arg("--experimental_allow_proto3_optional")
+        // Lets the crawler print crawl results as JSON.
+        .type_attribute(".crawler.ReturnJobRequest", "#[derive(serde::Serialize)]")
+        .field_attribute(
+            ".crawler.ReturnJobRequest.Ok.Body.Image.thumbnail",
+            "#[serde(skip_serializing)]",
+        )
+        .field_attribute(
+            ".crawler.ReturnJobRequest.Ok.Body.Image.content_hash",
+            "#[serde(serialize_with = \"crate::hex::serialize\")]",
+        )
+        .field_attribute(
+            ".crawler.ReturnJobRequest.Ok.Body.document_type",
+            "#[serde(serialize_with = \"crate::enum_name::document_type\")]",
+        )
+        .field_attribute(
+            ".crawler.ReturnJobRequest.Err.guard",
+            "#[serde(serialize_with = \"crate::enum_name::guard\")]",
+        )
+        .compile(
+            &[
+                "proto/search.proto",
+                "proto/admin.proto",
+                "proto/crawler.proto",
+            ],
+            &["proto"],
+        )?;
 
     Ok(())
 }
@@ -0,0 +1,19 @@
+use serde::Serializer;
+
+use crate::crawler::{DocumentType, Guard};
+
+/// Serializes a `DocumentType` field, which prost stores as an `i32`, by its name.
+pub fn document_type<S: Serializer>(value: &i32, serializer: S) -> Result<S::Ok, S::Error> {
+    match DocumentType::try_from(*value) {
+        Ok(document_type) => serializer.serialize_str(document_type.as_str_name()),
+        Err(_) => serializer.serialize_i32(*value),
+    }
+}
+
+/// Serializes a `Guard` field, which prost stores as an `i32`, by its name.
+pub fn guard<S: Serializer>(value: &i32, serializer: S) -> Result<S::Ok, S::Error> {
+    match Guard::try_from(*value) {
+        Ok(guard) => serializer.serialize_str(guard.as_str_name()),
+        Err(_) => serializer.serialize_i32(*value),
+    }
+}
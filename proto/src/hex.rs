@@ -0,0 +1,7 @@
+use serde::Serializer;
+
+/// Serializes bytes as a lowercase hex string, for hashes in JSON output.
+pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    serializer.serialize_str(&hex)
+}
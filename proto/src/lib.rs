@@ -6,9 +6,14 @@ pub mod admin {
     tonic::include_proto!("admin");
 }
 
+// `return_job_request::Result::Ok` holds a whole crawled page next to a small error.
+#[allow(clippy::large_enum_variant)]
 pub mod crawler {
     tonic::include_proto!("crawler");
 }
 
+pub mod enum_name;
+pub mod hex;
+
 pub use prost;
 pub use tonic;